@@ -1,45 +1,340 @@
-use cirrus_config::{config_key_path};
-use cirrus_egui::v1::{config_manager::ConfigManager, notifier::Notifier, ui_utils::combo_box::{self}, widgets::settings::{section::{Section, SectionDisplayInfo, SectionOverrides}, Settings}};
+use cirrus_egui::v1::{config_manager::ConfigManager, notifier::Notifier, ui_utils::combo_box::{self}, widgets::settings::Settings};
 use cirrus_theming::v1::Theme;
-use eframe::egui::{self, Align, Color32, Context, CursorIcon, Frame, Layout, Margin, RichText, Slider, Vec2};
-use egui::{include_image, Button, OpenUrl, Sense, Stroke, UiBuilder};
+use eframe::egui::{self, Align, Color32, Context, CursorIcon, Frame, Layout, Margin, Pos2, Rect, RichText, Slider, Vec2};
+use egui::{include_image, Button, OpenUrl, Sense, Stroke, StrokeKind, UiBuilder};
 use egui_notify::ToastLevel;
 use strum::IntoEnumIterator;
-use std::{time::Duration};
+use std::{path::Path, time::Duration};
 
-use crate::{config::config::Config, files, upscale::{OutputExt, Upscale}, windows::about::AboutWindow, Image, TEMPLATE_CONFIG_TOML_STRING};
+use crate::{config::{config::Config, layers::ConfigLayers}, files, processing::{alpha::AlphaStrategy, crop::Crop, encode::PngCompression, metadata::MetadataPolicy, tiling}, upscale::{self, OutputExt, Upscale}, plan, scheduler::{JobEvent, JobKind, JobState}, shortcuts::{Shortcut, Shortcuts}, theme::{self, Theming}, windows::{about::AboutWindow, compare::CompareWindow, history::HistoryWindow, plan::PlanWindow, session::{SessionAction, SessionWindow}, settings::SettingsPanel}, Image};
 
 pub struct Aeternum<'a> {
     theme: Theme,
+    theming: Theming,
     image: Option<Image>,
     about_box: AboutWindow<'a>,
+    history_window: HistoryWindow,
+    compare_window: CompareWindow,
+    plan_window: PlanWindow,
+    session_window: SessionWindow,
     notifier: Notifier,
     upscale: Upscale,
     config_manager: ConfigManager<Config>,
+    config_layers: ConfigLayers,
+    /// The user's config with the project config and `--config` merged over it.
+    config: Config,
+    shortcuts: Shortcuts,
+    /// What was wrong with the key binds when we last resolved them.
+    key_bind_problems: Vec<String>,
+    settings_panel: SettingsPanel,
 
     show_settings: bool,
+    crop_drag_start: Option<Pos2>,
+    /// Whether the preview shows the upscaled output rather than the source image.
+    preview_output: bool,
+    output_size: Vec2,
 }
 
 impl<'a> Aeternum<'a> {
-    pub fn new(image: Option<Image>, theme: Theme, notifier: Notifier, upscale: Upscale, config_manager: ConfigManager<Config>) -> Self {
-        let about_box = AboutWindow::new(&config_manager.config, &notifier);
+    pub fn new(
+        image: Option<Image>,
+        theme: Theme,
+        theming: Theming,
+        notifier: Notifier,
+        upscale: Upscale,
+        config_manager: ConfigManager<Config>,
+        config_layers: ConfigLayers
+    ) -> Self {
+        let config = config_layers.apply(&config_manager.config);
+        let mut key_bind_problems = Vec::new();
+        let shortcuts = Shortcuts::new(&config.keybinds, &notifier, &mut key_bind_problems);
+        let unfinished = upscale.session.lock().unwrap().unfinished();
 
         Self {
             image,
             theme,
+            theming,
             notifier,
-            about_box,
+            about_box: AboutWindow::new(),
+            history_window: HistoryWindow::new(),
+            compare_window: CompareWindow::new(),
+            plan_window: PlanWindow::new(),
+            session_window: SessionWindow::new(unfinished),
             upscale,
             config_manager,
+            config_layers,
+            config,
+            shortcuts,
+            key_bind_problems,
+            settings_panel: SettingsPanel::new(),
 
-            show_settings: false
+            show_settings: false,
+            crop_drag_start: None,
+            preview_output: false,
+            output_size: Vec2::ZERO
+        }
+    }
+
+    fn handle_job_event(&mut self, event: JobEvent) {
+        let seconds = self.upscale.job(event.id)
+            .and_then(|job| job.started_at)
+            .map_or(0, |started_at| started_at.elapsed().as_secs());
+
+        let kind = self.upscale.job(event.id).map_or(JobKind::Upscale, |job| job.kind);
+
+        match event.state {
+            // the compare window shows the comparison's results itself.
+            JobState::Succeeded { .. } if kind == JobKind::Compare => {
+                self.notifier.toast(
+                    "Finished comparing models!",
+                    ToastLevel::Success,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(5)));
+                    }
+                );
+            },
+            JobState::Succeeded { output } => {
+                // only the output of a job for the open image got kept, any other's is just toasted.
+                if self.upscale.output.as_ref() == Some(&output) {
+                    self.output_size = match imagesize::size(&output) {
+                        Ok(size) => Vec2::new(size.width as f32, size.height as f32),
+                        Err(_) => Vec2::ZERO
+                    };
+
+                    self.preview_output = self.config.misc.auto_preview_output;
+                }
+
+                self.notifier.toast(
+                    format!("Successfully upscaled image in {} seconds!", seconds),
+                    ToastLevel::Success,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(10)));
+                    }
+                );
+            },
+            JobState::Failed { error } => {
+                self.notifier.toast(
+                    Box::new(error),
+                    ToastLevel::Error,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(10)));
+                    }
+                );
+            },
+            JobState::Skipped { output } => {
+                self.notifier.toast(
+                    format!(
+                        "Skipped '{}' as it was already upscaled.",
+                        output.file_name().unwrap_or_default().to_string_lossy()
+                    ),
+                    ToastLevel::Info,
+                    |_| {}
+                );
+            },
+            JobState::Cancelled => {
+                self.notifier.toast(
+                    "Upscale cancelled.",
+                    ToastLevel::Info,
+                    |_| {}
+                );
+            },
+            _ => {}
+        }
+
+        // the loading spinner is shared so it only goes once the last job is done.
+        if self.upscale.idle() {
+            self.notifier.unset_loading();
+        }
+    }
+
+    fn set_image(&mut self, image: Option<Image>) {
+        if let Some(image) = &image {
+            self.use_project_config(&image.path);
+        }
+
+        self.image = image;
+        self.upscale.options.crop = None;
+        self.upscale.output = None;
+        self.preview_output = false;
+    }
+
+    /// Switches to the `aeternum.toml` nearest to the image if it's a different one than the last
+    /// image's, starting the upscale options over from that project's defaults.
+    fn use_project_config(&mut self, image_path: &Path) {
+        match self.config_layers.set_image(image_path) {
+            Ok(false) => return,
+            Ok(true) => {
+                if let Some(path) = self.config_layers.project_path() {
+                    self.notifier.toast(
+                        format!("Using the project config at '{}'.", path.display()),
+                        ToastLevel::Info,
+                        |_| {}
+                    );
+                }
+            },
+            Err(error) => {
+                self.notifier.toast(
+                    Box::new(error),
+                    ToastLevel::Error,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(10)));
+                    }
+                );
+            }
+        }
+
+        self.apply_config();
+
+        if let Err(error) = self.upscale.apply_defaults(&self.config.defaults) {
+            self.notifier.toast(
+                Box::new(error),
+                ToastLevel::Warning,
+                |_| {}
+            );
+        }
+    }
+
+    /// Merges the config layers over the user's config again after either of them changed.
+    fn apply_config(&mut self) {
+        self.config = self.config_layers.apply(&self.config_manager.config);
+        // a project config or --config can bring bad binds too. This runs on every change in the
+        // settings as well so only problems we haven't reported yet get toasted.
+        self.shortcuts = Shortcuts::new(&self.config.keybinds, &self.notifier, &mut self.key_bind_problems);
+    }
+
+    /// Rebuilds the theme when the OS's dark/light preference changes, if we're following it.
+    fn follow_system_theme(&mut self, ctx: &Context) {
+        if !self.theming.follows_system() {
+            return;
+        }
+
+        if let Some(system_theme) = ctx.system_theme() {
+            let is_dark = system_theme == egui::Theme::Dark;
+
+            if is_dark != self.theme.is_dark {
+                self.theme = self.theming.theme(Some(is_dark));
+                theme::apply(ctx, &self.theme);
+            }
+        }
+    }
+
+    fn open_image(&mut self, ctx: &Context) {
+        match files::select_image() {
+            Ok(image) => {
+                self.set_image(Some(image));
+                // I was able to get the memory of Aeternum to 
+                // 500 MB by just loading a different image after another.
+                // 
+                // Using "ctx.forget_all_images()" should be okay for now as this 
+                // also clears the "sparkles" gif that takes a very big amount of 
+                // memory that I also want cleared from memory as we won't be 
+                // seeing it again when we load an image.
+                ctx.forget_all_images();
+            },
+            Err(error) => {
+                self.notifier.toast(
+                    Box::new(error),
+                    ToastLevel::Error,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(5)));
+                    }
+                );
+            },
+        }
+    }
+
+    fn start_upscale(&mut self) {
+        if let (Some(image), Some(_)) = (self.image.clone(), &self.upscale.options.model) {
+            self.upscale.upscale(image, &self.notifier);
+        }
+    }
+
+    fn handle_shortcuts(&mut self, ctx: &Context) {
+        // keys typed into a text field are for the text field.
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        for shortcut in self.shortcuts.pressed(ctx, |shortcut| self.shortcut_runs(shortcut)) {
+            match shortcut {
+                Shortcut::AboutBox => self.about_box.show = !self.about_box.show,
+                Shortcut::OpenImage => self.open_image(ctx),
+                Shortcut::NewImage => self.set_image(None),
+                Shortcut::Upscale => self.start_upscale(),
+                Shortcut::Cancel => {
+                    let running: Vec<usize> = self.upscale.jobs().iter()
+                        .filter(|job| !job.state.finished())
+                        .map(|job| job.id)
+                        .collect();
+
+                    for id in running {
+                        self.upscale.cancel(id);
+                    }
+                },
+                Shortcut::NextModel | Shortcut::PreviousModel => {
+                    let models = &self.upscale.models;
+
+                    let current = self.upscale.options.model.as_ref()
+                        .and_then(|model| models.iter().position(|other| other.name == model.name));
+
+                    let index = match (shortcut, current) {
+                        (Shortcut::NextModel, Some(index)) => (index + 1) % models.len(),
+                        (Shortcut::PreviousModel, Some(index)) => (index + models.len() - 1) % models.len(),
+                        (Shortcut::NextModel, None) => 0,
+                        _ => models.len() - 1
+                    };
+
+                    self.upscale.options.model = Some(models[index].clone());
+                },
+                Shortcut::ScaleUp => {
+                    self.upscale.options.scale = (self.upscale.options.scale + 1).min(16);
+                },
+                Shortcut::ScaleDown => {
+                    self.upscale.options.scale = (self.upscale.options.scale - 1).max(1);
+                },
+                Shortcut::ToggleCompare => {
+                    self.preview_output = !self.preview_output;
+                },
+                Shortcut::OpenOutputFolder => {
+                    if let Some(output) = &self.upscale.output {
+                        if let Err(error) = files::reveal_in_folder(output) {
+                            self.notifier.toast(
+                                Box::new(error),
+                                ToastLevel::Error,
+                                |_| {}
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether the shortcut would do anything right now, the keys of those that
+    /// wouldn't are left alone (e.g. Escape still closes popups when nothing is running).
+    fn shortcut_runs(&self, shortcut: Shortcut) -> bool {
+        // the about box is the only thing that can be opened over the settings.
+        if self.show_settings {
+            return shortcut == Shortcut::AboutBox;
+        }
+
+        // same goes for everything that changes the options while they're greyed out.
+        let options_locked = self.upscale.busy() || self.image.is_none();
+
+        match shortcut {
+            Shortcut::Upscale | Shortcut::ScaleUp | Shortcut::ScaleDown => !options_locked,
+            Shortcut::NextModel | Shortcut::PreviousModel => !options_locked && !self.upscale.models.is_empty(),
+            Shortcut::Cancel => self.upscale.jobs().iter().any(|job| !job.state.finished()),
+            Shortcut::ToggleCompare | Shortcut::OpenOutputFolder => self.upscale.output.is_some(),
+            Shortcut::AboutBox | Shortcut::OpenImage | Shortcut::NewImage => true
         }
     }
 }
 
 impl<'a> eframe::App for Aeternum<'a> {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        self.about_box.handle_input(ctx);
+        self.follow_system_theme(ctx);
+        self.handle_shortcuts(ctx);
 
         // handles the settings panel closing and opening 
         // key binds inputs for us as well as saving on exit.
@@ -51,10 +346,51 @@ impl<'a> eframe::App for Aeternum<'a> {
         );
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            self.upscale.update();
+            let image_path = self.image.as_ref().map(|image| image.path.clone());
+
+            for event in self.upscale.update(image_path.as_deref()) {
+                self.handle_job_event(event);
+            }
+
             self.notifier.update(ctx);
             self.about_box.update(ctx);
 
+            self.compare_window.update(ctx, &mut self.upscale, self.image.as_ref(), &self.notifier);
+            self.plan_window.update(ctx);
+
+            match self.session_window.update(ctx) {
+                Some(SessionAction::Resume) => self.upscale.resume_session(&self.notifier),
+                Some(SessionAction::Discard(ids)) => self.upscale.session.lock().unwrap().discard(&ids),
+                None => {}
+            }
+
+            let rerun = self.history_window.update(ctx, &self.upscale.history, &self.notifier);
+
+            match rerun {
+                Some(_) if self.upscale.busy() => {
+                    self.notifier.toast(
+                        "Every worker is busy, wait for an upscale to finish first!",
+                        ToastLevel::Warning,
+                        |_| {}
+                    );
+                },
+                Some(entry) => match Image::from_path(entry.input) {
+                    Ok(image) => {
+                        self.set_image(Some(image.clone()));
+                        self.upscale.options = entry.options;
+                        self.upscale.upscale(image, &self.notifier);
+                    },
+                    Err(error) => {
+                        self.notifier.toast(
+                            Box::new(error),
+                            ToastLevel::Error,
+                            |_| {}
+                        );
+                    }
+                },
+                None => {}
+            }
+
             if self.show_settings {
                 // we only want to run the config manager's 
                 // update loop when were are in the settings menu
@@ -62,19 +398,18 @@ impl<'a> eframe::App for Aeternum<'a> {
 
                 let config = &mut self.config_manager.config;
 
-                Settings::new(TEMPLATE_CONFIG_TOML_STRING, &ui)
-                    .add_section(
-                        Section::new(
-                            config_key_path!(config.misc.enable_custom_folder),
-                            &mut config.misc.enable_custom_folder,
-                            SectionOverrides::default(),
-                            SectionDisplayInfo::default()
-                        )
-                    ).show_ui(ui, &self.theme);
+                if self.settings_panel.update(ui, config, &self.upscale.models, &self.config_layers) {
+                    self.apply_config();
+                }
 
                 return;
             }
 
+            // the workers are swapped out once we're out of the settings and nothing is running.
+            if self.upscale.workers != self.config.workers && self.upscale.idle() {
+                self.upscale.set_workers(&self.config.workers);
+            }
+
             let frame_margin = Margin {
                 left: 15,
                 right: 5,
@@ -121,7 +456,7 @@ impl<'a> eframe::App for Aeternum<'a> {
                     // TODO: find a way to fix scrollbar slightly covering 
                     // elements if even possible, I've already spent hours fixing this :( 
                     egui::ScrollArea::vertical().show(ui, |ui| {
-                        ui.add_enabled_ui(!self.upscale.upscaling && !self.image.is_none(), |ui| {
+                        ui.add_enabled_ui(!self.upscale.busy() && !self.image.is_none(), |ui| {
                             egui::Grid::new("options_grid")
                                 .spacing([0.0, 25.0])
                                 //.max_col_width(side_panel_size)
@@ -166,22 +501,105 @@ impl<'a> eframe::App for Aeternum<'a> {
 
                                         let scale = self.upscale.options.scale;
 
-                                        let image_size = match &self.image {
-                                            Some(image) => (image.image_size.width as i32, image.image_size.height as i32),
-                                            None => (0, 0),
+                                        let image_size = match (&self.image, &self.upscale.options.crop) {
+                                            (Some(_), Some(crop)) => (crop.width as i32, crop.height as i32),
+                                            (Some(image), None) => (image.image_size.width as i32, image.image_size.height as i32),
+                                            (None, _) => (0, 0),
                                         };
 
+                                        let output_size = (image_size.0 as u64 * scale as u64, image_size.1 as u64 * scale as u64);
+
                                         ui.label(
-                                            format!("({}x{})", image_size.0 * scale as i32, image_size.1 * scale as i32)
+                                            format!("({}x{})", output_size.0, output_size.1)
                                         );
+                                        ui.label(
+                                            RichText::new(
+                                                format!(
+                                                    "~{} in memory",
+                                                    format_bytes(tiling::memory_estimate(output_size.0, output_size.1))
+                                                )
+                                            ).size(10.0)
+                                        ).on_hover_text("Roughly how much memory the decoded upscaled image takes up.");
+
+                                        if let Some(image) = &self.image {
+                                            let output_pixels = output_size.0 * output_size.1;
+
+                                            let (duration, file_size) = match self.upscale.stats.try_lock() {
+                                                Ok(stats) => (
+                                                    self.upscale.options.model.as_ref().and_then(
+                                                        |model| stats.estimate_duration(
+                                                            &model.name, upscale::input_pixels(image, &self.upscale.options)
+                                                        )
+                                                    ),
+                                                    stats.estimate_file_size(&self.upscale.options.output_ext, output_pixels)
+                                                ),
+                                                Err(_) => (None, 0)
+                                            };
+
+                                            let duration_text = match duration {
+                                                Some(seconds) => format!("~{}", format_duration(seconds)),
+                                                None => "unknown time".to_string()
+                                            };
+
+                                            ui.label(
+                                                RichText::new(
+                                                    format!("{}, ~{} file", duration_text, format_bytes(file_size))
+                                                ).size(10.0)
+                                            ).on_hover_text(
+                                                "Estimated from past upscales on this machine, \
+                                                the time is unknown until the model has been used once."
+                                            );
+
+                                            let limits = &self.config.limits;
+                                            let mut warnings = Vec::new();
+
+                                            if limits.max_output_megapixels != 0 && output_pixels > limits.max_output_megapixels as u64 * 1_000_000 {
+                                                warnings.push(format!("over {} megapixels", limits.max_output_megapixels));
+                                            }
+
+                                            if limits.max_file_size_mb != 0 && file_size > limits.max_file_size_mb as u64 * 1024 * 1024 {
+                                                warnings.push(format!("over {} MB", limits.max_file_size_mb));
+                                            }
+
+                                            if let (Some(seconds), true) = (duration, limits.max_duration_minutes != 0) {
+                                                if seconds > limits.max_duration_minutes as f64 * 60.0 {
+                                                    warnings.push(format!("over {} minutes", limits.max_duration_minutes));
+                                                }
+                                            }
+
+                                            if !warnings.is_empty() {
+                                                ui.label(
+                                                    RichText::new(format!("⚠ Output is {}!", warnings.join(", ")))
+                                                        .size(10.0)
+                                                        .color(Color32::from_rgb(230, 160, 40))
+                                                ).on_hover_text("These limits can be changed in the [limits] section of the config.");
+                                            }
+                                        }
                                     });
                                     ui.end_row();
 
                                     ui.vertical_centered_justified(|ui| {
-                                        ui.label(RichText::new("Compression").size(20.0).strong());
-                                        ui.add(
-                                            Slider::new(&mut self.upscale.options.compression, 0..=100)
-                                        );
+                                        let tiling = &mut self.upscale.options.tiling;
+
+                                        ui.label(RichText::new("Tiling").size(20.0).strong());
+                                        ui.label(RichText::new("Upscale huge images piece by piece.").size(10.0));
+                                        ui.checkbox(&mut tiling.enabled, "Split into tiles");
+
+                                        if tiling.enabled {
+                                            ui.add(Slider::new(&mut tiling.tile_size, 256..=4096).text("tile size"));
+                                            ui.add(Slider::new(&mut tiling.overlap, 0..=128).text("overlap"));
+
+                                            if let Some(image) = &self.image {
+                                                let (width, height) = match &self.upscale.options.crop {
+                                                    Some(crop) => (crop.width, crop.height),
+                                                    None => (image.image_size.width as u32, image.image_size.height as u32)
+                                                };
+
+                                                ui.label(
+                                                    RichText::new(format!("{} tiles", tiling.plan(width, height).len())).size(10.0)
+                                                );
+                                            }
+                                        }
                                     });
                                     ui.end_row();
 
@@ -204,6 +622,114 @@ impl<'a> eframe::App for Aeternum<'a> {
                                                     );
                                                 }
                                             });
+
+                                        if self.image.as_ref().is_some_and(|image| image.animated) {
+                                            ui.label(RichText::new("Animations are saved as GIF unless WebP is picked.").size(10.0));
+                                        }
+                                    });
+                                    ui.end_row();
+
+                                    ui.vertical_centered_justified(|ui| {
+                                        let quality = &mut self.upscale.options.quality;
+
+                                        ui.label(RichText::new("Quality").size(20.0).strong());
+
+                                        match self.upscale.options.output_ext {
+                                            OutputExt::JPG => {
+                                                ui.label(RichText::new("Higher is better looking but bigger.").size(10.0));
+                                                ui.add(Slider::new(&mut quality.jpg_quality, 1..=100));
+
+                                                ui.horizontal(|ui| {
+                                                    ui.color_edit_button_srgb(&mut quality.jpg_background);
+                                                    ui.label("Background for transparency");
+                                                });
+                                            },
+                                            OutputExt::WebP => {
+                                                ui.label(RichText::new("Lossless keeps every pixel exactly.").size(10.0));
+                                                ui.checkbox(&mut quality.webp_lossless, "Lossless");
+
+                                                if !quality.webp_lossless {
+                                                    ui.add(Slider::new(&mut quality.webp_quality, 1..=100));
+                                                    ui.label(
+                                                        RichText::new(
+                                                            "Needs 'cwebp' (libwebp) when tiling or splitting off transparency."
+                                                        ).size(10.0)
+                                                    );
+                                                }
+                                            },
+                                            OutputExt::PNG => {
+                                                ui.label(RichText::new("PNG is lossless, this only trades speed for size.").size(10.0));
+
+                                                egui::ComboBox::from_id_salt("select_png_compression")
+                                                    .selected_text(quality.png_compression.to_string())
+                                                    .width(230.0)
+                                                    .show_ui(ui, |ui| {
+                                                        for compression in PngCompression::iter() {
+                                                            combo_box::ui_strong_selectable_value(
+                                                                ui,
+                                                                &mut quality.png_compression,
+                                                                compression,
+                                                                compression.to_string()
+                                                            );
+                                                        }
+                                                    });
+                                            },
+                                            OutputExt::AVIF => {
+                                                ui.label(RichText::new("Quality and encoding speed (lower is slower but smaller).").size(10.0));
+                                                ui.add(Slider::new(&mut quality.avif_quality, 1..=100).text("quality"));
+                                                ui.add(Slider::new(&mut quality.avif_speed, 1..=10).text("speed"));
+                                            },
+                                            OutputExt::TIFF => {
+                                                ui.label(RichText::new("TIFF is saved uncompressed.").size(10.0));
+                                            },
+                                            OutputExt::GIF => {
+                                                ui.label(RichText::new("GIF is limited to 256 colours.").size(10.0));
+                                            }
+                                        }
+                                    });
+                                    ui.end_row();
+
+                                    ui.vertical_centered_justified(|ui| {
+                                        ui.label(RichText::new("Transparency").size(20.0).strong());
+                                        ui.label(RichText::new("How to upscale the alpha channel.").size(10.0));
+
+                                        let selected_strategy = &self.upscale.options.alpha.to_string();
+
+                                        egui::ComboBox::from_id_salt("select_alpha")
+                                            .selected_text(selected_strategy)
+                                            .width(230.0)
+                                            .show_ui(ui, |ui| {
+                                                for strategy in AlphaStrategy::iter() {
+                                                    combo_box::ui_strong_selectable_value(
+                                                        ui,
+                                                        &mut self.upscale.options.alpha,
+                                                        strategy,
+                                                        strategy.to_string()
+                                                    );
+                                                }
+                                            });
+                                    });
+                                    ui.end_row();
+
+                                    ui.vertical_centered_justified(|ui| {
+                                        ui.label(RichText::new("Metadata").size(20.0).strong());
+                                        ui.label(RichText::new("EXIF, XMP and colour profile to keep.").size(10.0));
+
+                                        let selected_policy = &self.upscale.options.metadata.to_string();
+
+                                        egui::ComboBox::from_id_salt("select_metadata")
+                                            .selected_text(selected_policy)
+                                            .width(230.0)
+                                            .show_ui(ui, |ui| {
+                                                for policy in MetadataPolicy::iter() {
+                                                    combo_box::ui_strong_selectable_value(
+                                                        ui,
+                                                        &mut self.upscale.options.metadata,
+                                                        policy,
+                                                        policy.to_string()
+                                                    );
+                                                }
+                                            });
                                     });
                                     ui.end_row();
 
@@ -249,10 +775,21 @@ impl<'a> eframe::App for Aeternum<'a> {
                                         let upscale_button_response = ui.add_enabled(
                                             self.upscale.options.model.is_some(), upscale_button
                                         ).on_disabled_hover_text("No model selected.")
+                                        .on_hover_text(self.shortcuts.hint(Shortcut::Upscale))
                                         .on_hover_cursor(CursorIcon::PointingHand);
 
                                         if upscale_button_response.clicked() {
-                                            self.upscale.upscale(self.image.clone().unwrap(), &self.notifier);
+                                            self.start_upscale();
+                                        }
+
+                                        let plan_button_response = ui.button("Preview plan")
+                                            .on_hover_text("See where the output goes and anything that might go wrong.")
+                                            .on_hover_cursor(CursorIcon::PointingHand);
+
+                                        if let (true, Some(image)) = (plan_button_response.clicked(), &self.image) {
+                                            self.plan_window.open(
+                                                plan::plan(&[(image.path.clone(), self.upscale.options.clone())])
+                                            );
                                         }
                                     });
                                     ui.end_row();
@@ -270,41 +807,232 @@ impl<'a> eframe::App for Aeternum<'a> {
                 .show(ctx, |ui| {
                     ui.horizontal(|ui| {
                         ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                            let button = egui::Button::new(
+                            RichText::new("History").size(14.0)
+                            ).min_size(Vec2::new(70.0, 25.0));
+
+                            let response = ui.add(button)
+                                .on_hover_cursor(CursorIcon::PointingHand);
+
+                            if response.clicked() {
+                                self.history_window.show = !self.history_window.show;
+                            }
+
                             if self.image.is_some() {
+                                let button = egui::Button::new(
+                                RichText::new("Compare Models").size(14.0)
+                                ).min_size(Vec2::new(90.0, 25.0));
+
+                                let response = ui.add(button)
+                                    .on_hover_cursor(CursorIcon::PointingHand);
+
+                                if response.clicked() {
+                                    self.compare_window.show = !self.compare_window.show;
+                                }
+
                                 let button = egui::Button::new(
                                 RichText::new("New Image").size(14.0)
                                 ).min_size(Vec2::new(90.0, 25.0));
     
                                 let response = ui.add(button)
+                                    .on_hover_text(self.shortcuts.hint(Shortcut::NewImage))
                                     .on_hover_cursor(CursorIcon::PointingHand);
     
                                 if response.clicked() {
                                     // self.upscale.reset_options();
-                                    self.image = None;
+                                    self.set_image(None);
+                                }
+                            }
+
+                            if self.upscale.options.crop.is_some() {
+                                let button = egui::Button::new(
+                                RichText::new("Clear Crop").size(14.0)
+                                ).min_size(Vec2::new(90.0, 25.0));
+
+                                let response = ui.add_enabled(!self.upscale.busy(), button)
+                                    .on_hover_cursor(CursorIcon::PointingHand);
+
+                                if response.clicked() {
+                                    self.upscale.options.crop = None;
                                 }
                             }
                         });
                     });
                 }).response;
 
+            if let (Some(_), Some(output)) = (&self.image, self.upscale.output.clone()) {
+                egui::TopBottomPanel::bottom("output_bar")
+                    .show_separator_line(false)
+                    .frame(
+                        Frame::new()
+                            .inner_margin(Margin {left: 10, right: 10, bottom: 8, top: 4})
+                    )
+                    .show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(
+                                    format!("Saved to '{}'", output.file_name().unwrap_or_default().to_string_lossy())
+                                ).size(12.0)
+                            ).on_hover_text(output.to_string_lossy());
+
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                let compare_text = match self.preview_output {
+                                    true => "Show Original",
+                                    false => "Compare"
+                                };
+
+                                let compare_response = ui.button(compare_text)
+                                    .on_hover_text(self.shortcuts.hint(Shortcut::ToggleCompare))
+                                    .on_hover_cursor(CursorIcon::PointingHand);
+
+                                if compare_response.clicked() {
+                                    self.preview_output = !self.preview_output;
+                                }
+
+                                let mut result = Ok(());
+
+                                let folder_response = ui.button("Show in Folder")
+                                    .on_hover_text(self.shortcuts.hint(Shortcut::OpenOutputFolder))
+                                    .on_hover_cursor(CursorIcon::PointingHand);
+
+                                if folder_response.clicked() {
+                                    result = files::reveal_in_folder(&output);
+                                }
+
+                                if ui.button("Open").on_hover_cursor(CursorIcon::PointingHand).clicked() {
+                                    result = files::open_path(&output);
+                                }
+
+                                if let Err(error) = result {
+                                    self.notifier.toast(
+                                        Box::new(error),
+                                        ToastLevel::Error,
+                                        |_| {}
+                                    );
+                                }
+                            });
+                        });
+                    });
+            }
+
+            if !self.upscale.jobs().is_empty() {
+                egui::TopBottomPanel::bottom("jobs_panel")
+                    .frame(
+                        Frame::new()
+                            .inner_margin(Margin {left: 10, right: 10, bottom: 4, top: 6})
+                    )
+                    .show(ctx, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new("Jobs").size(14.0).strong());
+
+                            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                if ui.button("Clear Finished").on_hover_cursor(CursorIcon::PointingHand).clicked() {
+                                    self.upscale.clear_finished_jobs();
+                                }
+                            });
+                        });
+
+                        let mut cancel = None;
+
+                        egui::ScrollArea::vertical().max_height(90.0).show(ui, |ui| {
+                            for job in self.upscale.jobs().iter() {
+                                ui.horizontal(|ui| {
+                                    ui.label(
+                                        RichText::new(
+                                            job.input.file_name().unwrap_or_default().to_string_lossy()
+                                        ).size(12.0)
+                                    ).on_hover_text(job.input.to_string_lossy());
+
+                                    if let Some(backend) = &job.backend {
+                                        ui.label(RichText::new(backend.label()).size(10.0).weak());
+                                    }
+
+                                    let state = match &job.state {
+                                        JobState::Idle => RichText::new("Waiting").weak(),
+                                        JobState::Preparing => RichText::new("Preparing..."),
+                                        JobState::Running { progress } => RichText::new(progress),
+                                        JobState::PostProcessing => RichText::new("Finishing up..."),
+                                        JobState::Succeeded { .. } => RichText::new("Done").color(Color32::LIGHT_GREEN),
+                                        JobState::Skipped { .. } => RichText::new("Skipped").weak(),
+                                        JobState::Failed { .. } => RichText::new("Failed").color(Color32::LIGHT_RED),
+                                        JobState::Cancelled => RichText::new("Cancelled").weak()
+                                    };
+
+                                    let response = ui.label(state.size(12.0));
+
+                                    if let JobState::Failed { error } = &job.state {
+                                        response.on_hover_text(error.to_string());
+                                    }
+
+                                    if !job.state.finished() && ui.small_button("Cancel").clicked() {
+                                        cancel = Some(job.id);
+                                    }
+                                });
+                            }
+                        });
+
+                        if let Some(id) = cancel {
+                            self.upscale.cancel(id);
+                        }
+                    });
+            }
+
             egui::CentralPanel::default().show(ctx, |ui| {
                 match self.image.as_ref() {
                     Some(image) => {
-                        let image_path = format!("file://{}", image.path.to_string_lossy());
+                        let (image_path, image_size) = match (&self.upscale.output, self.preview_output) {
+                            (Some(output), true) => (output, self.output_size),
+                            _ => (
+                                &image.input_path,
+                                Vec2::new(image.image_size.width as f32, image.image_size.height as f32)
+                            )
+                        };
 
-                        ui.centered_and_justified(|ui| {
+                        let image_path = format!("file://{}", image_path.to_string_lossy());
+
+                        let response = ui.centered_and_justified(|ui| {
                             ui.add(
                                 egui::Image::from_uri(image_path)
                                     .corner_radius(8.0)
                                     .shrink_to_fit()
-                                    .max_size(
-                                        [image.image_size.width as f32, image.image_size.height as f32].into()
-                                    )
+                                    .max_size(image_size)
+                                    .sense(Sense::drag())
                             )
-                        });
+                        }).inner;
+
+                        // Dragging over the preview selects the region of the image to upscale.
+                        if !self.upscale.busy() && !self.preview_output {
+                            if response.drag_started() {
+                                self.crop_drag_start = response.interact_pointer_pos();
+                            }
+
+                            if let (Some(start), Some(current)) = (self.crop_drag_start, response.interact_pointer_pos()) {
+                                let selection = Rect::from_two_pos(start, current).intersect(response.rect);
+
+                                self.upscale.options.crop = screen_rect_to_crop(selection, response.rect, image_size);
+                            }
+
+                            if response.drag_stopped() {
+                                self.crop_drag_start = None;
+                            }
+                        }
+
+                        if let (Some(crop), false) = (&self.upscale.options.crop, self.preview_output) {
+                            ui.painter().rect_stroke(
+                                crop_to_screen_rect(crop, response.rect, image_size),
+                                0.0,
+                                Stroke {
+                                    width: 2.0,
+                                    color: Color32::from_hex(
+                                        &self.theme.accent_colour.hex_code
+                                    ).unwrap()
+                                },
+                                StrokeKind::Outside
+                            );
+                        }
                     },
                     None => {
-                        // Collect dropped files. UNTESTED!
+                        // Collect dropped files.
                         ctx.input(|i| {
                             let dropped_files = &i.raw.dropped_files;
 
@@ -316,7 +1044,9 @@ impl<'a> eframe::App for Aeternum<'a> {
                                     .unwrap(); // Umm I wonder why "PathBuf" is optional (Optional<T>) here.
 
                                 match Image::from_path(path.clone()) {
-                                    Ok(image) => self.image = Some(image),
+                                    Ok(image) => {
+                                        self.set_image(Some(image));
+                                    },
                                     Err(error) => {
                                         self.notifier.toast(
                                             Box::new(error),
@@ -353,7 +1083,9 @@ impl<'a> eframe::App for Aeternum<'a> {
                                 .corner_radius(20.0);
 
                                 let button_response = ui.add(button);
-                                button_response.clone().on_hover_cursor(CursorIcon::PointingHand);
+                                button_response.clone()
+                                    .on_hover_text(self.shortcuts.hint(Shortcut::OpenImage))
+                                    .on_hover_cursor(CursorIcon::PointingHand);
                                 //button_rect = button_response.rect;
                                 ui.add_space(8.0);
 
@@ -368,30 +1100,7 @@ impl<'a> eframe::App for Aeternum<'a> {
                                 );
 
                                 if button_response.clicked() {
-                                    let image_result = files::select_image();
-
-                                    match image_result {
-                                        Ok(image) => {
-                                            self.image = Some(image);
-                                            // I was able to get the memory of Aeternum to 
-                                            // 500 MB by just loading a different image after another.
-                                            // 
-                                            // Using "ctx.forget_all_images()" should be okay for now as this 
-                                            // also clears the "sparkles" gif that takes a very big amount of 
-                                            // memory that I also want cleared from memory as we won't be 
-                                            // seeing it again when we load an image.
-                                            ctx.forget_all_images();
-                                        },
-                                        Err(error) => {
-                                            self.notifier.toast(
-                                                Box::new(error),
-                                                ToastLevel::Error,
-                                                |toast| {
-                                                    toast.duration(Some(Duration::from_secs(5)));
-                                                }
-                                            );
-                                        },
-                                    }
+                                    self.open_image(ctx);
                                 }
                             }).response;
 
@@ -468,3 +1177,63 @@ impl<'a> eframe::App for Aeternum<'a> {
             });
     }
 }
+
+/// Converts a selection on the preview into a crop in image pixels.
+fn screen_rect_to_crop(selection: Rect, image_rect: Rect, image_size: Vec2) -> Option<Crop> {
+    let scale = image_size / image_rect.size();
+
+    let min = ((selection.min - image_rect.min) * scale).round();
+    let size = (selection.size() * scale).round();
+
+    if size.x < 1.0 || size.y < 1.0 {
+        return None;
+    }
+
+    Some(
+        Crop {
+            x: min.x as u32,
+            y: min.y as u32,
+            width: size.x.min(image_size.x - min.x) as u32,
+            height: size.y.min(image_size.y - min.y) as u32
+        }
+    )
+}
+
+/// Converts a crop in image pixels back to where it sits on the preview.
+fn crop_to_screen_rect(crop: &Crop, image_rect: Rect, image_size: Vec2) -> Rect {
+    let scale = image_rect.size() / image_size;
+
+    Rect::from_min_size(
+        image_rect.min + Vec2::new(crop.x as f32, crop.y as f32) * scale,
+        Vec2::new(crop.width as f32, crop.height as f32) * scale
+    )
+}
+
+/// Formats a byte count in the largest unit that keeps it above one (e.g. "1.5 GB").
+fn format_bytes(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, units[unit])
+}
+
+/// Formats a duration in seconds for the ETA (e.g. "42s", "3m 5s" or "1h 20m").
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, (seconds % 3600) / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests;
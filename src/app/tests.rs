@@ -0,0 +1,469 @@
+//! Drives the app frame by frame with egui_kittest, finding widgets through
+//! the accessibility tree egui builds and poking them like a user would.
+
+use std::{fs, path::Path, thread, time::{Duration, Instant}};
+
+use cirrus_egui::v1::{config_manager::ConfigManager, notifier::Notifier};
+use cirrus_theming::v1::Theme;
+use eframe::egui::{self, accesskit::{Action, ActionData, ActionRequest, Role}, DroppedFile, Event, Key, Modifiers, Vec2};
+use egui_kittest::{kittest::{NodeT, Queryable}, Harness, Node};
+
+use crate::{config::layers::ConfigLayers, setup_context, test_utils::{write_image, TestFolder}, theme::Theming, upscale::Upscale, Image};
+
+use super::Aeternum;
+
+type AppHarness = Harness<'static, Aeternum<'static>>;
+
+fn new_harness(image: Option<Image>) -> AppHarness {
+    Harness::builder()
+        .with_size(Vec2::new(800.0, 600.0))
+        .build_eframe(|cc| {
+            let theme = Theme::new(true, vec![], None);
+
+            setup_context(&cc.egui_ctx, &theme);
+
+            Aeternum::new(
+                image,
+                theme,
+                Theming::default(),
+                Notifier::new(),
+                Upscale::mock(),
+                ConfigManager::default(),
+                ConfigLayers::default()
+            )
+        })
+}
+
+/// Runs whatever input was queued up plus a few frames so popups have settled, what the
+/// input changed only gets drawn the frame after. Toasts and spinners keep asking for
+/// repaints so this can't wait for the app to stop repainting.
+fn settle(harness: &mut AppHarness) {
+    harness.step();
+    harness.run_ok();
+}
+
+/// The first widget labelled `text`, labels keep their text as the value instead.
+fn find<'a>(harness: &'a AppHarness, text: &'a str) -> Option<Node<'a>> {
+    harness.query_all_by(
+        move |node| node.label().as_deref() == Some(text) || node.value().as_deref() == Some(text)
+    ).next()
+}
+
+fn get<'a>(harness: &'a AppHarness, text: &'a str) -> Node<'a> {
+    find(harness, text).unwrap_or_else(|| panic!("No widget labelled '{}'!", text))
+}
+
+fn enabled(harness: &AppHarness, text: &str) -> bool {
+    !get(harness, text).accesskit_node().is_disabled()
+}
+
+fn nth_with_role(harness: &AppHarness, role: Role, index: usize) -> Node<'_> {
+    harness.get_all_by_role(role)
+        .nth(index)
+        .unwrap_or_else(|| panic!("There's no {:?} number {}!", role, index))
+}
+
+/// Clicks through accesskit rather than the pointer so widgets scrolled out of view can be clicked too.
+fn click(harness: &mut AppHarness, text: &str) {
+    get(harness, text).click_accesskit();
+    settle(harness);
+}
+
+fn press(harness: &mut AppHarness, key: Key) {
+    press_with(harness, Modifiers::NONE, key);
+}
+
+fn press_with(harness: &mut AppHarness, modifiers: Modifiers, key: Key) {
+    harness.key_press_modifiers(modifiers, key);
+    settle(harness);
+}
+
+fn select_model(harness: &mut AppHarness, name: &str) {
+    nth_with_role(harness, Role::ComboBox, 0).click_accesskit();
+    settle(harness);
+
+    click(harness, name);
+}
+
+fn set_scale(harness: &mut AppHarness, scale: f64) {
+    let target = nth_with_role(harness, Role::Slider, 0).accesskit_node().id();
+
+    harness.input_mut().events.push(
+        Event::AccessKitActionRequest(
+            ActionRequest { action: Action::SetValue, target, data: Some(ActionData::NumericValue(scale)) }
+        )
+    );
+    settle(harness);
+}
+
+/// Focuses the `index`th text field and replaces everything in it with `text`.
+fn type_into(harness: &mut AppHarness, index: usize, text: &str) {
+    let field = nth_with_role(harness, Role::TextInput, index);
+
+    field.focus();
+    harness.key_press_modifiers(Modifiers::COMMAND, Key::A);
+    field.type_text(text);
+
+    settle(harness);
+}
+
+fn drop_file(harness: &mut AppHarness, path: &Path) {
+    harness.input_mut().dropped_files.push(
+        DroppedFile {
+            path: Some(path.to_path_buf()),
+            ..Default::default()
+        }
+    );
+    settle(harness);
+}
+
+/// Keeps running frames until every job is done.
+fn wait_for_jobs(harness: &mut AppHarness) {
+    let started = Instant::now();
+
+    while !harness.state().upscale.idle() {
+        assert!(started.elapsed() < Duration::from_secs(30), "The upscale never finished!");
+
+        thread::sleep(Duration::from_millis(10));
+        harness.step();
+    }
+
+    settle(harness);
+}
+
+/// An app with `photo.png` (16x12) already opened from a fresh folder.
+fn harness_with_image(name: &str) -> (AppHarness, TestFolder) {
+    let folder = TestFolder::new(name);
+    let input = folder.join("photo.png");
+
+    write_image(&input, 16, 12);
+
+    let harness = new_harness(Some(Image::from_path(input).unwrap()));
+
+    (harness, folder)
+}
+
+#[test]
+fn shows_open_image_without_an_image() {
+    let harness = new_harness(None);
+
+    assert!(find(&harness, "Open Image").is_some());
+    assert!(find(&harness, "Pick an image to upscale...").is_some());
+    assert!(!enabled(&harness, "Upscale"));
+}
+
+#[test]
+fn dropping_a_file_opens_it() {
+    let folder = TestFolder::new("dropping_a_file_opens_it");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 16, 12);
+
+    let mut harness = new_harness(None);
+
+    drop_file(&mut harness, &input);
+
+    assert_eq!(harness.state().image.as_ref().map(|image| image.path.clone()), Some(input));
+    assert!(find(&harness, "Open Image").is_none());
+    assert!(find(&harness, "(64x48)").is_some());
+}
+
+#[test]
+fn dropping_a_file_uses_its_project_config() {
+    let folder = TestFolder::new("dropping_a_file_uses_its_project_config");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 16, 12);
+    fs::write(folder.join("aeternum.toml"), "[defaults]\nmodel = \"mock-x4\"\nscale = 2\n").unwrap();
+
+    let mut harness = new_harness(None);
+
+    drop_file(&mut harness, &input);
+
+    assert_eq!(harness.state().upscale.options.model.as_ref().map(|model| model.name.as_str()), Some("mock-x4"));
+    assert_eq!(harness.state().upscale.options.scale, 2);
+    assert!(find(&harness, "(32x24)").is_some());
+    assert!(enabled(&harness, "Upscale"));
+}
+
+#[test]
+fn reports_bad_key_binds_in_a_project_config() {
+    let folder = TestFolder::new("reports_bad_key_binds_in_a_project_config");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 16, 12);
+    fs::write(folder.join("aeternum.toml"), "[keybinds]\nupscale = \"Ctrl+Nope\"\ncancel = \"A\"\n").unwrap();
+
+    let mut harness = new_harness(None);
+
+    assert!(harness.state().key_bind_problems.is_empty());
+
+    drop_file(&mut harness, &input);
+
+    let problems = &harness.state().key_bind_problems;
+
+    assert_eq!(problems.len(), 2, "{:?}", problems);
+    assert!(problems[0].contains("'Ctrl+Nope' for 'upscale' is invalid"));
+    assert!(problems[1].contains("'about_box' and 'cancel'"));
+}
+
+#[test]
+fn dropping_something_that_isnt_an_image_keeps_the_start_screen() {
+    let folder = TestFolder::new("dropping_not_an_image");
+    let input = folder.join("notes.txt");
+
+    fs::write(&input, "not an image").unwrap();
+
+    let mut harness = new_harness(None);
+
+    drop_file(&mut harness, &input);
+
+    assert!(harness.state().image.is_none());
+    assert!(find(&harness, "Open Image").is_some());
+}
+
+#[test]
+fn selecting_a_model_enables_upscale() {
+    let (mut harness, _folder) = harness_with_image("selecting_a_model");
+
+    assert!(harness.state().upscale.options.model.is_none());
+    assert!(!enabled(&harness, "Upscale"));
+
+    select_model(&mut harness, "mock-x4");
+
+    assert_eq!(harness.state().upscale.options.model.as_ref().map(|model| model.name.as_str()), Some("mock-x4"));
+    assert!(enabled(&harness, "Upscale"));
+}
+
+#[test]
+fn changing_the_scale_updates_the_output_size() {
+    let (mut harness, _folder) = harness_with_image("changing_the_scale");
+
+    assert!(find(&harness, "(64x48)").is_some());
+
+    set_scale(&mut harness, 2.0);
+
+    assert_eq!(harness.state().upscale.options.scale, 2);
+    assert!(find(&harness, "(32x24)").is_some());
+}
+
+#[test]
+fn upscales_and_shows_the_result() {
+    let (mut harness, folder) = harness_with_image("upscales_and_shows_the_result");
+
+    select_model(&mut harness, "mock-x4");
+    set_scale(&mut harness, 2.0);
+
+    click(&mut harness, "Upscale");
+    wait_for_jobs(&mut harness);
+
+    let output = folder.join("photo_mock-x4_x2.png");
+
+    assert_eq!(harness.state().upscale.output, Some(output.clone()));
+    assert_eq!(harness.state().output_size, Vec2::new(32.0, 24.0));
+    assert!(output.exists());
+
+    assert!(find(&harness, "Saved to 'photo_mock-x4_x2.png'").is_some());
+    assert!(find(&harness, "Done").is_some());
+
+    // the output is only previewed once asked for as "auto_preview_output" is off by default.
+    assert!(!harness.state().preview_output);
+
+    click(&mut harness, "Compare");
+    assert!(harness.state().preview_output);
+
+    click(&mut harness, "Show Original");
+    assert!(!harness.state().preview_output);
+}
+
+#[test]
+fn switching_images_drops_the_last_images_output() {
+    let (mut harness, folder) = harness_with_image("switching_images_drops_the_output");
+    let other = folder.join("other.png");
+
+    write_image(&other, 16, 12);
+
+    select_model(&mut harness, "mock-x4");
+    click(&mut harness, "Upscale");
+
+    // the job may or may not have finished by now, it's for the wrong image either way.
+    harness.state_mut().set_image(Some(Image::from_path(other).unwrap()));
+    wait_for_jobs(&mut harness);
+
+    assert!(harness.state().upscale.output.is_none());
+    assert!(find(&harness, "Saved to 'photo_mock-x4_x4.png'").is_none());
+}
+
+#[test]
+fn failed_upscale_shows_in_the_jobs_panel() {
+    let (mut harness, _folder) = harness_with_image("failed_upscale");
+
+    select_model(&mut harness, "mock-fail");
+
+    click(&mut harness, "Upscale");
+    wait_for_jobs(&mut harness);
+
+    assert!(harness.state().upscale.output.is_none());
+    assert!(find(&harness, "Failed").is_some());
+
+    click(&mut harness, "Clear Finished");
+    assert!(find(&harness, "Failed").is_none());
+}
+
+#[test]
+fn compares_models_on_the_scheduler() {
+    let (mut harness, _folder) = harness_with_image("compares_models_on_the_scheduler");
+
+    harness.state_mut().compare_window.show = true;
+    settle(&mut harness);
+
+    click(&mut harness, "mock-x4");
+    click(&mut harness, "mock-fail");
+    click(&mut harness, "Run");
+
+    // it goes through the scheduler, so on the mock backend, like an upscale would.
+    assert_eq!(harness.state().upscale.jobs().len(), 1);
+
+    wait_for_jobs(&mut harness);
+
+    assert!(harness.state().upscale.output.is_none());
+    assert!(find(&harness, "Ground truth").is_some());
+    assert!(find(&harness, "failed").is_some());
+    assert!(find(&harness, "Done").is_some());
+}
+
+#[test]
+fn settings_panel_replaces_the_options() {
+    let (mut harness, _folder) = harness_with_image("settings_panel");
+
+    assert!(find(&harness, "Preview plan").is_some());
+
+    // cirrus' "Settings::handle_input" opens and closes the settings with Ctrl+Comma.
+    press_with(&mut harness, Modifiers::COMMAND, Key::Comma);
+
+    assert!(harness.state().show_settings);
+    assert!(find(&harness, "Preview plan").is_none());
+    assert!(find(&harness, "Key Binds").is_some());
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::Comma);
+
+    assert!(!harness.state().show_settings);
+    assert!(find(&harness, "Preview plan").is_some());
+}
+
+#[test]
+fn about_box_key_bind_toggles_it() {
+    let mut harness = new_harness(None);
+
+    assert!(!harness.state().about_box.show);
+
+    press(&mut harness, Key::A);
+    assert!(harness.state().about_box.show);
+
+    press(&mut harness, Key::A);
+    assert!(!harness.state().about_box.show);
+}
+
+#[test]
+fn shortcuts_change_the_model_and_scale() {
+    let (mut harness, _folder) = harness_with_image("shortcuts_change_the_model_and_scale");
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowDown);
+    assert_eq!(harness.state().upscale.options.model.as_ref().map(|model| model.name.as_str()), Some("mock-x4"));
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowDown);
+    assert_eq!(harness.state().upscale.options.model.as_ref().map(|model| model.name.as_str()), Some("mock-fail"));
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowUp);
+    assert_eq!(harness.state().upscale.options.model.as_ref().map(|model| model.name.as_str()), Some("mock-x4"));
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowLeft);
+    assert_eq!(harness.state().upscale.options.scale, 3);
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowRight);
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowRight);
+    assert_eq!(harness.state().upscale.options.scale, 5);
+}
+
+#[test]
+fn shortcuts_upscale_and_toggle_compare() {
+    let (mut harness, _folder) = harness_with_image("shortcuts_upscale_and_toggle_compare");
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::ArrowDown);
+    press_with(&mut harness, Modifiers::COMMAND, Key::Enter);
+    wait_for_jobs(&mut harness);
+
+    assert!(harness.state().upscale.output.is_some());
+
+    press(&mut harness, Key::C);
+    assert!(harness.state().preview_output);
+
+    press_with(&mut harness, Modifiers::COMMAND, Key::N);
+    assert!(harness.state().image.is_none());
+}
+
+#[test]
+fn settings_only_take_valid_key_binds() {
+    let (mut harness, _folder) = harness_with_image("settings_only_take_valid_key_binds");
+
+    harness.state_mut().show_settings = true;
+    settle(&mut harness);
+
+    // the key binds come first in the settings and "upscale" is the fourth of them.
+    type_into(&mut harness, 3, "Ctrl+Nope");
+    assert!(find(&harness, "'Ctrl+Nope' isn't a key bind, try something like \"Ctrl+O\".").is_some());
+    assert_eq!(harness.state().config_manager.config.keybinds.upscale, "Ctrl+Enter");
+
+    type_into(&mut harness, 3, "Ctrl+U");
+    assert_eq!(harness.state().config_manager.config.keybinds.upscale, "Ctrl+U");
+
+    type_into(&mut harness, 0, "Ctrl+U");
+    assert!(find(&harness, "`Ctrl+U` is already bound to 'upscale'.").is_some());
+    assert_eq!(harness.state().config_manager.config.keybinds.about_box, "A");
+
+    click(&mut harness, "Reset to defaults");
+    assert_eq!(harness.state().config_manager.config.keybinds.upscale, "Ctrl+Enter");
+    assert!(find(&harness, "`Ctrl+U` is already bound to 'upscale'.").is_none());
+}
+
+#[test]
+fn settings_reset_a_section_to_its_defaults() {
+    let (mut harness, _folder) = harness_with_image("settings_reset_a_section");
+
+    harness.state_mut().config_manager.config.limits.max_file_size_mb = 1;
+    harness.state_mut().config_manager.config.workers.concurrency = 3;
+    harness.state_mut().show_settings = true;
+    settle(&mut harness);
+
+    // "Output" is the fourth of the sections.
+    harness.get_all_by_label("Reset to defaults").nth(3).unwrap().click_accesskit();
+    settle(&mut harness);
+
+    assert_eq!(harness.state().config_manager.config.limits.max_file_size_mb, 500);
+    assert_eq!(harness.state().config_manager.config.workers.concurrency, 3);
+
+    // leaving the settings brings the workers in line with the config.
+    harness.state_mut().show_settings = false;
+    settle(&mut harness);
+
+    assert_eq!(harness.state().upscale.workers.concurrency, 3);
+}
+
+#[test]
+fn follows_the_system_theme() {
+    let mut harness = new_harness(None);
+
+    assert!(harness.state().theme.is_dark);
+
+    harness.input_mut().system_theme = Some(egui::Theme::Light);
+    settle(&mut harness);
+
+    assert!(!harness.state().theme.is_dark);
+    assert!(!harness.ctx.style().visuals.dark_mode);
+
+    harness.input_mut().system_theme = Some(egui::Theme::Dark);
+    settle(&mut harness);
+
+    assert!(harness.state().theme.is_dark);
+    assert!(harness.ctx.style().visuals.dark_mode);
+}
\ No newline at end of file
@@ -0,0 +1,231 @@
+use std::{env, fs, path::PathBuf, sync::{Arc, Mutex}, time::Instant};
+
+use serde::Serialize;
+
+use crate::{config::structs::workers::Workers, error::Error, scheduler::JobKind, upscale::{self, Backend, OutputExt, Progress, Stage, Upscale, UpscaleOptions}};
+
+static SAMPLE_IMAGE: &[u8] = include_bytes!("../assets/crystal.png");
+
+#[derive(clap::Args, Debug)]
+pub struct BenchArgs {
+    /// Image to benchmark with, defaults to a bundled 512x512 sample.
+    image: Option<PathBuf>,
+
+    /// upscayl-bin tile sizes to try, 0 lets upscayl-bin pick one. Only that one's
+    /// timings feed the estimates as it's what a normal upscale uses.
+    #[arg(long, value_delimiter = ',', default_values_t = [0, 128, 256, 512])]
+    tile_sizes: Vec<u32>,
+
+    #[arg(short, long, default_value_t = 4)]
+    scale: i8,
+
+    /// Print the results as JSON instead of a table.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BenchResult {
+    pub model: String,
+    pub tile_size: u32,
+    pub seconds: f64,
+    /// Peak resident memory of upscayl-bin by its last progress line, only measured on linux.
+    pub peak_memory_bytes: Option<u64>,
+    pub output_bytes: u64,
+    pub succeeded: bool
+}
+
+/// Runs every model at every tile size, one at a time on the scheduler so the runs don't slow
+/// each other down. Only the runs at the automatic tile size go into the stats used for
+/// estimates, as that's the only one a normal upscale uses the rest would skew them.
+pub fn run(args: &BenchArgs, upscale: &mut Upscale) -> Result<Vec<BenchResult>, Error> {
+    let folder = env::temp_dir().join("aeternum").join("bench");
+
+    fs::create_dir_all(&folder).map_err(
+        |error| Error::FailedToBenchmark(Some(error.to_string()), "Failed to create the bench folder.".to_string())
+    )?;
+
+    let input = match &args.image {
+        Some(path) => path.clone(),
+        None => {
+            let sample_path = folder.join("sample.png");
+
+            fs::write(&sample_path, SAMPLE_IMAGE).map_err(
+                |error| Error::FailedToBenchmark(Some(error.to_string()), "Failed to write the sample image.".to_string())
+            )?;
+
+            sample_path
+        }
+    };
+
+    let size = imagesize::size(&input).map_err(
+        |error| Error::FailedToBenchmark(Some(error.to_string()), "Failed to get the image size.".to_string())
+    )?;
+
+    let input_pixels = size.width as u64 * size.height as u64;
+    let results = Arc::new(Mutex::new(Vec::new()));
+
+    upscale.set_workers(&Workers { concurrency: 1, ..upscale.workers.clone() });
+
+    for model in upscale.models.clone() {
+        for tile_size in args.tile_sizes.iter().copied() {
+            let model = model.clone();
+            let input = input.clone();
+            let output = folder.join(format!("{}_{}.png", model.name, tile_size));
+            let scale = args.scale;
+            let stats = upscale.stats.clone();
+            let results = results.clone();
+
+            upscale.queue_work(
+                JobKind::Bench,
+                input.clone(),
+                Box::new(BenchProgress),
+                Box::new(move |backend, progress| {
+                    eprintln!("Benchmarking '{}' with tile size {}...", model.name, tile_size);
+
+                    let backend = Backend {
+                        tile_size: Some(tile_size),
+                        ..backend.clone()
+                    };
+
+                    let options = UpscaleOptions {
+                        model: Some(model.clone()),
+                        scale,
+                        ..Default::default()
+                    };
+
+                    let _ = fs::remove_file(&output);
+
+                    let now = Instant::now();
+                    let result = upscale::run_backend(&backend, &input, &output, &options, 0, "Benchmarking", progress);
+                    let seconds = now.elapsed().as_secs_f64();
+
+                    let output_bytes = fs::metadata(&output).map(|metadata| metadata.len()).unwrap_or(0);
+                    let succeeded = result.is_ok() && output_bytes > 0;
+
+                    if succeeded && tile_size == 0 {
+                        stats.lock().unwrap().record(
+                            &model.name,
+                            &OutputExt::PNG,
+                            input_pixels,
+                            input_pixels * (scale as u64).pow(2),
+                            seconds,
+                            output_bytes
+                        );
+                    }
+
+                    let _ = fs::remove_file(&output);
+
+                    results.lock().unwrap().push(
+                        BenchResult {
+                            model: model.name.clone(),
+                            tile_size,
+                            seconds,
+                            peak_memory_bytes: result.as_ref().ok().copied().flatten(),
+                            output_bytes,
+                            succeeded
+                        }
+                    );
+
+                    result.map(|_| output)
+                })
+            );
+        }
+    }
+
+    // there's only the one worker so the results come in the order they were queued.
+    while upscale.next_event().is_some() {}
+
+    let results = std::mem::take(&mut *results.lock().unwrap());
+
+    Ok(results)
+}
+
+pub fn print(args: &BenchArgs, results: &[BenchResult]) {
+    if args.json {
+        match serde_json::to_string_pretty(results) {
+            Ok(json) => println!("{}", json),
+            Err(error) => eprintln!("Failed to serialize the results! Error: {}", error)
+        }
+
+        return;
+    }
+
+    let name_width = results.iter()
+        .map(|result| result.model.len())
+        .max()
+        .unwrap_or(0)
+        .max("model".len());
+
+    println!(
+        "{:<name_width$}  {:>9}  {:>9}  {:>12}  {:>12}",
+        "model", "tile size", "time (s)", "peak memory", "output size"
+    );
+
+    for result in results {
+        let tile_size = match result.tile_size {
+            0 => "auto".to_string(),
+            tile_size => tile_size.to_string()
+        };
+
+        let seconds = match result.succeeded {
+            true => format!("{:.2}", result.seconds),
+            false => "failed".to_string()
+        };
+
+        let peak_memory = match result.peak_memory_bytes {
+            Some(bytes) => format!("{} MB", bytes / 1024 / 1024),
+            None => "-".to_string()
+        };
+
+        println!(
+            "{:<name_width$}  {:>9}  {:>9}  {:>12}  {:>12}",
+            result.model,
+            tile_size,
+            seconds,
+            peak_memory,
+            format!("{} KB", result.output_bytes / 1024)
+        );
+    }
+}
+
+/// Benchmarks only print which run they're on, upscayl-bin's progress would drown out the results.
+struct BenchProgress;
+
+impl Progress for BenchProgress {
+    fn stage(&self, _stage: Stage, _message: String) {}
+
+    fn warning(&self, error: Error) {
+        eprintln!("Warning: {}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::{write_image, TestFolder};
+
+    use super::*;
+
+    #[test]
+    fn benches_every_model_and_tile_size_on_the_scheduler() {
+        let folder = TestFolder::new("bench");
+        let image = folder.join("image.png");
+
+        write_image(&image, 16, 12);
+
+        let args = BenchArgs { image: Some(image), tile_sizes: vec![0, 64], scale: 2, json: true };
+        let mut upscale = Upscale::mock();
+
+        let results = run(&args, &mut upscale).unwrap();
+
+        let runs: Vec<(&str, u32, bool)> = results.iter()
+            .map(|result| (result.model.as_str(), result.tile_size, result.succeeded))
+            .collect();
+
+        assert_eq!(runs, [("mock-x4", 0, true), ("mock-x4", 64, true), ("mock-fail", 0, false), ("mock-fail", 64, false)]);
+        assert_eq!(upscale.jobs().len(), 4);
+
+        // the run at the automatic tile size feeds the estimates.
+        assert!(upscale.stats.lock().unwrap().estimate_duration("mock-x4", 192).is_some());
+    }
+}
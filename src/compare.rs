@@ -0,0 +1,169 @@
+use std::{env, fs, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::Instant};
+
+use cirrus_egui::v1::notifier::Notifier;
+use image::imageops::FilterType;
+
+use crate::{error::Error, history, image::Image, processing::{crop::Crop, metrics}, scheduler::JobKind, upscale::{self, Backend, Model, Progress, Stage, Upscale, UpscaleOptions}};
+
+/// How one model did at recreating the ground truth.
+#[derive(Debug, Clone)]
+pub struct ModelScore {
+    pub model: String,
+    pub output: PathBuf,
+    pub psnr: f64,
+    pub ssim: f64,
+    pub sharpness: f64,
+    pub seconds: f64
+}
+
+#[derive(Debug, Default)]
+pub struct Comparison {
+    pub ground_truth: Option<PathBuf>,
+    pub scores: Vec<ModelScore>,
+    /// Models upscayl-bin failed on along with why.
+    pub failed: Vec<(String, String)>
+}
+
+/// Queues a job that scores `models` by downscaling the image (or its crop) by `scale`, upscaling
+/// it back with each model and measuring how close they get to the original. Returns the job's id.
+pub fn run(
+    upscale: &mut Upscale,
+    models: Vec<Model>,
+    image: &Image,
+    crop: Option<Crop>,
+    scale: i8,
+    notifier: &Notifier,
+    comparison: Arc<Mutex<Comparison>>
+) -> usize {
+    *comparison.lock().unwrap() = Comparison::default();
+
+    let input_path = image.input_path.clone();
+
+    upscale.queue_work(
+        JobKind::Compare,
+        image.path.clone(),
+        Box::new(notifier.clone()),
+        Box::new(
+            move |backend, progress| compare_models(backend, &models, &input_path, crop, scale, progress, &comparison)
+        )
+    )
+}
+
+/// Returns the folder the ground truth and every model's output were written to.
+fn compare_models(
+    backend: &Backend,
+    models: &[Model],
+    input_path: &Path,
+    crop: Option<Crop>,
+    scale: i8,
+    progress: &dyn Progress,
+    comparison: &Arc<Mutex<Comparison>>
+) -> Result<PathBuf, Error> {
+    let compare_error = |error: String, reason: &str| Error::FailedToCompareModels(Some(error), reason.to_string());
+
+    progress.stage(Stage::Preparing, "Preparing ground truth...".to_string());
+
+    let compare_folder = env::temp_dir().join("aeternum").join("compare");
+    let _ = fs::remove_dir_all(&compare_folder);
+
+    // a new folder each run so the preview doesn't show images cached from the last one.
+    let folder = compare_folder.join(history::unix_timestamp().to_string());
+
+    fs::create_dir_all(&folder).map_err(|error| compare_error(error.to_string(), "Failed to create the compare folder."))?;
+
+    let mut source = image::open(input_path).map_err(
+        |error| compare_error(error.to_string(), "Failed to read the image.")
+    )?;
+
+    if let Some(crop) = crop {
+        source = source.crop_imm(crop.x, crop.y, crop.width, crop.height);
+    }
+
+    let scale = scale.max(1) as u32;
+    let (width, height) = (source.width() / scale, source.height() / scale);
+
+    if width == 0 || height == 0 {
+        return Err(
+            Error::FailedToCompareModels(
+                None, format!("The image is too small to downscale by {}.", scale)
+            )
+        );
+    }
+
+    // trimmed so the upscaled outputs line up with it pixel for pixel.
+    let ground_truth = source.crop_imm(0, 0, width * scale, height * scale);
+    let downscaled = ground_truth.resize_exact(width, height, FilterType::Lanczos3);
+
+    let ground_truth_path = folder.join("ground_truth.png");
+    let downscaled_path = folder.join("downscaled.png");
+
+    ground_truth.save(&ground_truth_path).map_err(
+        |error| compare_error(error.to_string(), "Failed to save the ground truth.")
+    )?;
+    downscaled.save(&downscaled_path).map_err(
+        |error| compare_error(error.to_string(), "Failed to save the downscaled image.")
+    )?;
+
+    comparison.lock().unwrap().ground_truth = Some(ground_truth_path);
+
+    let ground_truth_rgb = ground_truth.to_rgb8();
+    let ground_truth_luma = ground_truth.to_luma8();
+
+    for (index, model) in models.iter().enumerate() {
+        // the scheduler reports the job as cancelled whatever we return.
+        if progress.cancelled() {
+            break;
+        }
+
+        let options = UpscaleOptions {
+            model: Some(model.clone()),
+            scale: scale as i8,
+            ..Default::default()
+        };
+
+        let output = folder.join(format!("{}.png", model.name));
+        let now = Instant::now();
+
+        let result = upscale::run_backend(
+            backend,
+            &downscaled_path,
+            &output,
+            &options,
+            0,
+            &format!("Comparing '{}' ({}/{})", model.name, index + 1, models.len()),
+            progress
+        );
+
+        let seconds = now.elapsed().as_secs_f64();
+
+        let upscaled = match result {
+            Ok(_) => image::open(&output).map_err(|error| error.to_string()),
+            Err(error) => Err(error.to_string())
+        };
+
+        let upscaled = match upscaled {
+            Ok(upscaled) => upscaled.resize_exact(
+                ground_truth.width(), ground_truth.height(), FilterType::Lanczos3
+            ),
+            Err(error) => {
+                comparison.lock().unwrap().failed.push((model.name.clone(), error));
+                continue;
+            }
+        };
+
+        let upscaled_luma = upscaled.to_luma8();
+
+        let score = ModelScore {
+            model: model.name.clone(),
+            output,
+            psnr: metrics::psnr(&upscaled.to_rgb8(), &ground_truth_rgb),
+            ssim: metrics::ssim(&upscaled_luma, &ground_truth_luma),
+            sharpness: metrics::sharpness(&upscaled_luma),
+            seconds
+        };
+
+        comparison.lock().unwrap().scores.push(score);
+    }
+
+    Ok(folder)
+}
@@ -1,20 +1,49 @@
 use cirrus_config::v1::config::CConfig;
 use serde::{Deserialize, Serialize};
 
-use crate::config::structs::{key_binds::KeyBinds, misc::Misc};
+use crate::config::{migrate::CONFIG_VERSION, structs::{backend::BackendPaths, defaults::Defaults, key_binds::KeyBinds, limits::Limits, misc::Misc, models::Models, theme::ThemeConfig, workers::Workers}};
 
-#[derive(Debug, Deserialize, Serialize, Default, Clone, Hash)]
+#[derive(Debug, Deserialize, Serialize, Clone, Hash)]
 pub struct Config {
     #[serde(default = "version_default")]
     pub version: i8,
     #[serde(default)]
     pub keybinds: KeyBinds,
     #[serde(default)]
-    pub misc: Misc
+    pub models: Models,
+    #[serde(default)]
+    pub misc: Misc,
+    #[serde(default)]
+    pub limits: Limits,
+    #[serde(default)]
+    pub workers: Workers,
+    #[serde(default)]
+    pub backend: BackendPaths,
+    #[serde(default)]
+    pub defaults: Defaults,
+    #[serde(default)]
+    pub theme: ThemeConfig
+}
+
+// written out so a default config is at the current version rather than 0.
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: version_default(),
+            keybinds: KeyBinds::default(),
+            models: Models::default(),
+            misc: Misc::default(),
+            limits: Limits::default(),
+            workers: Workers::default(),
+            backend: BackendPaths::default(),
+            defaults: Defaults::default(),
+            theme: ThemeConfig::default()
+        }
+    }
 }
 
 impl CConfig for Config {}
 
 fn version_default() -> i8 {
-    1
+    CONFIG_VERSION
 }
\ No newline at end of file
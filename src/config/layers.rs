@@ -0,0 +1,291 @@
+use std::{fmt::Write, fs, path::{Path, PathBuf}};
+
+use cirrus_path::v1::get_user_config_dir_path;
+use toml::{Table, Value};
+
+use crate::{config::{config::Config, migrate}, error::Error, APP_NAME};
+
+/// The name of config files that apply to every image in their folder and the folders under it.
+pub static PROJECT_CONFIG_NAME: &str = "aeternum.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayerKind {
+    User,
+    Project,
+    Override
+}
+
+/// A config file's options, only the ones set in it.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub kind: LayerKind,
+    pub path: PathBuf,
+    table: Table
+}
+
+impl Layer {
+    /// Reads the config at `path`, making sure every option it sets is one we can use.
+    pub fn load(path: &Path, kind: LayerKind) -> Result<Self, Error> {
+        let to_error = |error: toml::de::Error| Error::FailedToLoadConfig(
+            Some(error.to_string()), path.to_path_buf(), error.message().to_string()
+        );
+
+        let string = fs::read_to_string(path)
+            .map_err(|error| Error::FailedToLoadConfig(Some(error.to_string()), path.to_path_buf(), "Failed to read it.".to_string()))?;
+
+        let table = string.parse::<Table>().map_err(to_error)?;
+
+        // merging over the defaults catches options set to the wrong type now rather than every time it's applied.
+        let mut merged = default_table();
+        merge(&mut merged, &table);
+
+        merged.try_into::<Config>().map_err(to_error)?;
+
+        for key in migrate::unknown_keys(&table) {
+            log::warn!("'{}' in '{}' isn't an option we know of, it'll be ignored.", key, path.display());
+        }
+
+        Ok(Self { kind, path: path.to_path_buf(), table })
+    }
+
+    pub fn name(&self) -> String {
+        let kind = match self.kind {
+            LayerKind::User => "user config",
+            LayerKind::Project => "project config",
+            LayerKind::Override => "--config"
+        };
+
+        format!("{} ({})", kind, self.path.display())
+    }
+}
+
+/// Config files merged over the user's config, the one given with `--config` wins over
+/// the project config (the nearest `aeternum.toml` to the image) which wins over the user's.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLayers {
+    project: Option<Layer>,
+    over: Option<Layer>
+}
+
+impl ConfigLayers {
+    pub fn load(config_path: Option<&Path>, image_path: Option<&Path>) -> Result<Self, Error> {
+        let over = match config_path {
+            Some(path) if !path.is_file() => return Err(
+                Error::FileNotFound(None, path.to_path_buf(), "The config given with --config doesn't exist.".to_string())
+            ),
+            Some(path) => Some(Layer::load(path, LayerKind::Override)?),
+            None => None
+        };
+
+        let mut layers = Self { project: None, over };
+
+        if let Some(image_path) = image_path {
+            layers.set_image(image_path)?;
+        }
+
+        Ok(layers)
+    }
+
+    /// Swaps in the project config for `image_path`, returning whether it's a different one than before.
+    pub fn set_image(&mut self, image_path: &Path) -> Result<bool, Error> {
+        let path = find_project_config(image_path);
+
+        if path.as_ref() == self.project.as_ref().map(|layer| &layer.path) {
+            return Ok(false);
+        }
+
+        // forget the last project's config even if this one fails to load.
+        self.project = None;
+
+        if let Some(path) = path {
+            self.project = Some(Layer::load(&path, LayerKind::Project)?);
+        }
+
+        Ok(true)
+    }
+
+    pub fn project_path(&self) -> Option<&Path> {
+        self.project.as_ref().map(|layer| layer.path.as_path())
+    }
+
+    /// Every layer that's loaded, the one that wins last.
+    pub fn layers(&self) -> impl Iterator<Item = &Layer> {
+        self.project.iter().chain(self.over.iter())
+    }
+
+    /// `config` with every layer merged over it.
+    pub fn apply(&self, config: &Config) -> Config {
+        let mut table = match Value::try_from(config) {
+            Ok(Value::Table(table)) => table,
+            _ => return config.clone()
+        };
+
+        for layer in self.layers() {
+            merge(&mut table, &layer.table);
+        }
+
+        // every layer was checked against the config when it was loaded so this shouldn't fail.
+        table.try_into().unwrap_or_else(|error| {
+            log::error!("Failed to merge the project configs, ignoring them! Error: {}", error);
+            config.clone()
+        })
+    }
+
+    /// The merged config as TOML with a comment after each option telling where it was set.
+    pub fn describe(&self, config: &Config) -> String {
+        let user = user_layer();
+
+        let mut sources: Vec<&Layer> = self.layers().collect();
+        sources.reverse();
+        sources.extend(user.iter());
+
+        let table = match Value::try_from(self.apply(config)) {
+            Ok(Value::Table(table)) => table,
+            _ => Table::new()
+        };
+
+        let mut string = String::from("# The config Aeternum would use, with where each option was set.\n");
+
+        describe_table(&table, "", &sources, &mut string);
+
+        string
+    }
+}
+
+/// The nearest `aeternum.toml` to the image, looking in its folder and then each folder above it.
+pub fn find_project_config(image_path: &Path) -> Option<PathBuf> {
+    let image_path = std::path::absolute(image_path).ok()?;
+
+    image_path.ancestors()
+        .skip(1)
+        .map(|folder| folder.join(PROJECT_CONFIG_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Merges `over` into `base`, tables are merged key by key and everything else is replaced.
+fn merge(base: &mut Table, over: &Table) {
+    for (key, value) in over.iter() {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base)), Value::Table(over)) => merge(base, over),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+fn default_table() -> Table {
+    match Value::try_from(Config::default()) {
+        Ok(Value::Table(table)) => table,
+        _ => Table::new()
+    }
+}
+
+/// The user's config file as it's written, so we can tell the options they set from defaults.
+fn user_layer() -> Option<Layer> {
+    let path = match get_user_config_dir_path(APP_NAME) {
+        Ok(config_dir_path) => config_dir_path.join("config.toml"),
+        Err(error) => {
+            log::warn!("Failed to get the config path! Error: {}", error.human_message());
+            return None;
+        }
+    };
+
+    let table = fs::read_to_string(&path).ok()?.parse::<Table>().ok()?;
+
+    Some(Layer { kind: LayerKind::User, path, table })
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+
+    Some(value)
+}
+
+fn describe_table(table: &Table, prefix: &str, sources: &[&Layer], string: &mut String) {
+    // options have to come before any tables or they'd end up in the last one.
+    for (key, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+        let path = format!("{}{}", prefix, key);
+
+        let source = sources.iter()
+            .find(|layer| lookup(&layer.table, &path).is_some())
+            .map_or("default".to_string(), |layer| layer.name());
+
+        let _ = writeln!(string, "{} = {}  # {}", key, value, source);
+    }
+
+    for (key, value) in table.iter() {
+        if let Value::Table(table) = value {
+            let path = format!("{}{}", prefix, key);
+
+            let _ = writeln!(string, "\n[{}]", path);
+
+            describe_table(table, &format!("{}.", path), sources, string);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::TestFolder;
+
+    use super::*;
+
+    #[test]
+    fn finds_the_nearest_project_config() {
+        let folder = TestFolder::new("layers-nearest");
+        let shoot = folder.join("client").join("shoot");
+
+        fs::create_dir_all(&shoot).unwrap();
+        fs::write(folder.join(PROJECT_CONFIG_NAME), "").unwrap();
+
+        assert_eq!(find_project_config(&shoot.join("photo.png")), Some(folder.join(PROJECT_CONFIG_NAME)));
+
+        fs::write(shoot.join(PROJECT_CONFIG_NAME), "").unwrap();
+
+        assert_eq!(find_project_config(&shoot.join("photo.png")), Some(shoot.join(PROJECT_CONFIG_NAME)));
+    }
+
+    #[test]
+    fn override_wins_over_the_project_config() {
+        let folder = TestFolder::new("layers-override");
+        let over = folder.join("override.toml");
+
+        fs::write(folder.join(PROJECT_CONFIG_NAME), "[defaults]\nscale = 2\nformat = \"JPG\"\n").unwrap();
+        fs::write(&over, "[defaults]\nscale = 3\n").unwrap();
+
+        let layers = ConfigLayers::load(Some(&over), Some(&folder.join("photo.png"))).unwrap();
+
+        let mut user = Config::default();
+        user.defaults.model = "realesrgan-x4plus".to_string();
+        user.defaults.format = "WebP".to_string();
+
+        let config = layers.apply(&user);
+
+        assert_eq!(config.defaults.scale, 3);
+        assert_eq!(config.defaults.format, "JPG");
+        assert_eq!(config.defaults.model, "realesrgan-x4plus");
+
+        let description = layers.describe(&user);
+
+        assert!(description.contains(&format!("scale = 3  # --config ({})", over.display())));
+        assert!(description.contains("format = \"JPG\"  # project config ("));
+    }
+
+    #[test]
+    fn rejects_options_of_the_wrong_type() {
+        let folder = TestFolder::new("layers-wrong-type");
+        let path = folder.join(PROJECT_CONFIG_NAME);
+
+        fs::write(&path, "[defaults]\nscale = \"big\"\n").unwrap();
+
+        let error = ConfigLayers::load(None, Some(&folder.join("photo.png"))).unwrap_err();
+
+        assert_eq!(error.kind(), "FailedToLoadConfig");
+    }
+}
\ No newline at end of file
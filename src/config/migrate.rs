@@ -0,0 +1,276 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use cirrus_path::v1::get_user_config_dir_path;
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, Item};
+
+use crate::{config::config::Config, error::Error, APP_NAME};
+
+/// The version configs are written at, bump this whenever a step is added to `STEPS`.
+pub const CONFIG_VERSION: i8 = 2;
+
+/// Steps upgrading a config by one version, the first one takes version 1 to 2 and so on.
+/// They edit the file as it's written so the user's comments and layout survive.
+const STEPS: [fn(&mut DocumentMut); 1] = [
+    move_custom_folder_to_models
+];
+
+/// What loading the config turned up that the user should hear about.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// The version the config was upgraded from and where the old file was backed up to.
+    pub migrated: Option<(i8, PathBuf)>,
+    /// Keys we don't know about so they do nothing, e.g. "misc.auto_preveiw_output".
+    pub unknown_keys: Vec<String>,
+    /// Set to the config's version when it was written by a newer Aeternum than this one.
+    pub newer_version: Option<i8>
+}
+
+impl Report {
+    pub fn notes(&self) -> Vec<String> {
+        match &self.migrated {
+            Some((version, backup)) => vec![
+                format!(
+                    "Your config was upgraded from version {} to {}, the old one was backed up to '{}'.",
+                    version,
+                    CONFIG_VERSION,
+                    backup.display()
+                )
+            ],
+            None => Vec::new()
+        }
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings: Vec<String> = self.unknown_keys.iter()
+            .map(|key| format!("'{}' in your config isn't an option we know of, it'll be ignored.", key))
+            .collect();
+
+        if let Some(version) = self.newer_version {
+            warnings.push(
+                format!(
+                    "Your config is at version {} which is newer than this Aeternum knows (version {}), some options may be ignored.",
+                    version,
+                    CONFIG_VERSION
+                )
+            );
+        }
+
+        warnings
+    }
+}
+
+/// Upgrades the user's config file before it's loaded, see `migrate_file`.
+pub fn migrate_user_config() -> Result<Report, Error> {
+    let path = match get_user_config_dir_path(APP_NAME) {
+        Ok(config_dir_path) => config_dir_path.join("config.toml"),
+        Err(error) => return Err(
+            Error::FailedToMigrateConfig(
+                Some(error.human_message()), PathBuf::from("config.toml"), "Failed to get the config folder.".to_string()
+            )
+        )
+    };
+
+    migrate_file(&path)
+}
+
+/// Upgrades the config at `path` to `CONFIG_VERSION` if it's older, backing the old
+/// file up next to it first, and looks for keys that don't belong to any option.
+pub fn migrate_file(path: &Path) -> Result<Report, Error> {
+    let to_error = |error: String, reason: &str| Error::FailedToMigrateConfig(
+        Some(error), path.to_path_buf(), reason.to_string()
+    );
+
+    // no config yet, the config manager writes a fresh one from the template.
+    let string = match fs::read_to_string(path) {
+        Ok(string) => string,
+        Err(_) => return Ok(Report::default())
+    };
+
+    let table = string.parse::<Table>()
+        .map_err(|error| to_error(error.to_string(), "The config isn't valid TOML."))?;
+
+    let mut report = Report {
+        unknown_keys: unknown_keys(&table),
+        ..Default::default()
+    };
+
+    let mut document = string.parse::<DocumentMut>()
+        .map_err(|error| to_error(error.to_string(), "The config isn't valid TOML."))?;
+
+    let version = version(&document);
+
+    if version > CONFIG_VERSION {
+        report.newer_version = Some(version);
+    }
+
+    if let Some(from) = migrate(&mut document) {
+        let backup = path.with_file_name(format!("config.v{}.bak.toml", from));
+
+        fs::copy(path, &backup)
+            .map_err(|error| to_error(error.to_string(), "Failed to back up the old config."))?;
+
+        let upgraded = document.to_string();
+
+        fs::write(path, &upgraded)
+            .map_err(|error| to_error(error.to_string(), "Failed to write the upgraded config."))?;
+
+        report.migrated = Some((from, backup));
+        // keys the steps moved somewhere else aren't unknown anymore.
+        report.unknown_keys = upgraded.parse::<Table>()
+            .map_or_else(|_| Vec::new(), |table| unknown_keys(&table));
+    }
+
+    Ok(report)
+}
+
+/// Runs every step between the config's version and ours, returning the version it was at if anything ran.
+pub fn migrate(document: &mut DocumentMut) -> Option<i8> {
+    let from = version(document);
+
+    if from >= CONFIG_VERSION {
+        return None;
+    }
+
+    for step in STEPS.iter().skip((from.max(1) - 1) as usize) {
+        step(document);
+    }
+
+    document["version"] = toml_edit::value(CONFIG_VERSION as i64);
+
+    Some(from)
+}
+
+/// Configs from before versioning was read don't always have a version, those are version 1.
+fn version(document: &DocumentMut) -> i8 {
+    document.get("version")
+        .and_then(Item::as_integer)
+        .map_or(1, |version| version as i8)
+}
+
+/// Dotted paths of every key in `table` that isn't in our config.
+pub fn unknown_keys(table: &Table) -> Vec<String> {
+    let known = match Value::try_from(Config::default()) {
+        Ok(Value::Table(known)) => known,
+        _ => return Vec::new()
+    };
+
+    let mut unknown = Vec::new();
+
+    collect_unknown_keys(table, &known, "", &mut unknown);
+
+    unknown
+}
+
+fn collect_unknown_keys(table: &Table, known: &Table, prefix: &str, unknown: &mut Vec<String>) {
+    for (key, value) in table.iter() {
+        let path = format!("{}{}", prefix, key);
+
+        match (value, known.get(key)) {
+            (_, None) => unknown.push(path),
+            (Value::Table(table), Some(Value::Table(known))) => {
+                collect_unknown_keys(table, known, &format!("{}.", path), unknown);
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Version 2 gave models their own table, "misc.enable_custom_folder" became "models.custom_folder".
+fn move_custom_folder_to_models(document: &mut DocumentMut) {
+    let enabled = document.get_mut("misc")
+        .and_then(Item::as_table_like_mut)
+        .and_then(|misc| misc.remove("enable_custom_folder"));
+
+    if let Some(enabled) = enabled {
+        let models = document.entry("models").or_insert(toml_edit::table());
+
+        if let Some(models) = models.as_table_like_mut() {
+            models.insert("custom_folder", enabled);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils::TestFolder;
+
+    use super::*;
+
+    const VERSION_1: &str = r#"
+version = 1
+
+[keybinds]
+# I keep hitting A by accident.
+about_box = "B"
+
+[misc]
+enable_custom_folder = false
+auto_preview_output = true
+"#;
+
+    #[test]
+    fn upgrades_version_1() {
+        let mut document = VERSION_1.parse::<DocumentMut>().unwrap();
+
+        assert_eq!(migrate(&mut document), Some(1));
+        assert_eq!(document["version"].as_integer(), Some(CONFIG_VERSION as i64));
+        assert_eq!(document["models"]["custom_folder"].as_bool(), Some(false));
+        assert!(document["misc"].get("enable_custom_folder").is_none());
+
+        let config: Config = toml::from_str(&document.to_string()).unwrap();
+
+        assert!(!config.models.custom_folder);
+        assert!(config.misc.auto_preview_output);
+        assert_eq!(config.keybinds.about_box, "B");
+    }
+
+    #[test]
+    fn leaves_current_configs_alone() {
+        let config = Config::default();
+
+        assert_eq!(config.version, CONFIG_VERSION);
+
+        let string = toml::to_string(&config).unwrap();
+        let mut document = string.parse::<DocumentMut>().unwrap();
+
+        assert_eq!(migrate(&mut document), None);
+        assert_eq!(document.to_string(), string);
+    }
+
+    #[test]
+    fn finds_unknown_keys() {
+        let table = r#"
+version = 2
+colour = "red"
+
+[misc]
+auto_preveiw_output = true
+
+[workers]
+concurrency = 2
+"#.parse::<Table>().unwrap();
+
+        assert_eq!(unknown_keys(&table), ["colour", "misc.auto_preveiw_output"]);
+    }
+
+    #[test]
+    fn backs_up_the_old_file() {
+        let folder = TestFolder::new("migrate-backup");
+        let path = folder.join("config.toml");
+
+        fs::write(&path, VERSION_1).unwrap();
+
+        let report = migrate_file(&path).unwrap();
+        let backup = folder.join("config.v1.bak.toml");
+
+        assert_eq!(report.migrated, Some((1, backup.clone())));
+        assert!(report.unknown_keys.is_empty());
+        assert_eq!(fs::read_to_string(&backup).unwrap(), VERSION_1);
+
+        let upgraded = fs::read_to_string(&path).unwrap();
+
+        assert!(upgraded.contains("custom_folder = false"));
+        assert!(upgraded.contains("# I keep hitting A by accident.\nabout_box = \"B\""));
+    }
+}
\ No newline at end of file
@@ -1,2 +1,4 @@
 pub mod config;
-mod structs;
\ No newline at end of file
+pub mod layers;
+pub mod migrate;
+pub mod structs;
\ No newline at end of file
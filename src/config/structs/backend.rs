@@ -0,0 +1,10 @@
+use serde::{Serialize, Deserialize};
+
+/// Where upscayl-bin and its models are, empty leaves it to where we'd look by default.
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, Default)]
+pub struct BackendPaths {
+    #[serde(default)]
+    pub upscayl_bin: String,
+    #[serde(default)]
+    pub models_folder: String
+}
\ No newline at end of file
@@ -0,0 +1,43 @@
+use serde::{Serialize, Deserialize};
+
+/// Options every image starts out with, in the app and on the command line.
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct Defaults {
+    /// Name of the model to select, empty selects none in the app and the first one on the command line.
+    #[serde(default)]
+    pub model: String,
+    #[serde(default = "scale_default")]
+    pub scale: i8,
+    #[serde(default = "format_default")]
+    pub format: String,
+    /// What metadata from the source ends up in the upscale: "Keep all", "Keep colour only" or "Strip all".
+    #[serde(default = "metadata_default")]
+    pub metadata: String,
+    /// Empty saves upscales next to the image.
+    #[serde(default)]
+    pub output_folder: String
+}
+
+impl Default for Defaults {
+    fn default() -> Self {
+        Self {
+            model: String::new(),
+            scale: scale_default(),
+            format: format_default(),
+            metadata: metadata_default(),
+            output_folder: String::new()
+        }
+    }
+}
+
+fn scale_default() -> i8 {
+    4
+}
+
+fn format_default() -> String {
+    "PNG".to_string()
+}
+
+fn metadata_default() -> String {
+    "Keep all".to_string()
+}
\ No newline at end of file
@@ -1,19 +1,91 @@
 use serde::{Serialize, Deserialize};
 
+/// Key binds as written in the config, a key name optionally
+/// prefixed by modifiers (e.g. "A", "Ctrl+O" or "Ctrl+Shift+O").
 #[derive(Debug, Serialize, Deserialize, Clone, Hash)]
 pub struct KeyBinds {
     #[serde(default = "about_box_default")]
     pub about_box: String,
+    #[serde(default = "open_image_default")]
+    pub open_image: String,
+    #[serde(default = "new_image_default")]
+    pub new_image: String,
+    #[serde(default = "upscale_default")]
+    pub upscale: String,
+    #[serde(default = "cancel_default")]
+    pub cancel: String,
+    #[serde(default = "next_model_default")]
+    pub next_model: String,
+    #[serde(default = "previous_model_default")]
+    pub previous_model: String,
+    #[serde(default = "scale_up_default")]
+    pub scale_up: String,
+    #[serde(default = "scale_down_default")]
+    pub scale_down: String,
+    #[serde(default = "toggle_compare_default")]
+    pub toggle_compare: String,
+    #[serde(default = "open_output_folder_default")]
+    pub open_output_folder: String,
 }
 
 impl Default for KeyBinds {
     fn default() -> Self {
         Self {
-            about_box: about_box_default()
+            about_box: about_box_default(),
+            open_image: open_image_default(),
+            new_image: new_image_default(),
+            upscale: upscale_default(),
+            cancel: cancel_default(),
+            next_model: next_model_default(),
+            previous_model: previous_model_default(),
+            scale_up: scale_up_default(),
+            scale_down: scale_down_default(),
+            toggle_compare: toggle_compare_default(),
+            open_output_folder: open_output_folder_default()
         }
     }
 }
 
 fn about_box_default() -> String {
     "A".to_string()
+}
+
+fn open_image_default() -> String {
+    "Ctrl+O".to_string()
+}
+
+fn new_image_default() -> String {
+    "Ctrl+N".to_string()
+}
+
+fn upscale_default() -> String {
+    "Ctrl+Enter".to_string()
+}
+
+fn cancel_default() -> String {
+    "Ctrl+Period".to_string()
+}
+
+fn next_model_default() -> String {
+    "Ctrl+ArrowDown".to_string()
+}
+
+fn previous_model_default() -> String {
+    "Ctrl+ArrowUp".to_string()
+}
+
+fn scale_up_default() -> String {
+    "Ctrl+ArrowRight".to_string()
+}
+
+fn scale_down_default() -> String {
+    "Ctrl+ArrowLeft".to_string()
+}
+
+fn toggle_compare_default() -> String {
+    "C".to_string()
+}
+
+fn open_output_folder_default() -> String {
+    "Ctrl+Shift+O".to_string()
 }
\ No newline at end of file
@@ -0,0 +1,34 @@
+use serde::{Serialize, Deserialize};
+
+/// Thresholds past which we warn before upscaling, 0 disables a limit.
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct Limits {
+    #[serde(default = "max_output_megapixels_default")]
+    pub max_output_megapixels: u32,
+    #[serde(default = "max_file_size_mb_default")]
+    pub max_file_size_mb: u32,
+    #[serde(default = "max_duration_minutes_default")]
+    pub max_duration_minutes: u32
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_output_megapixels: max_output_megapixels_default(),
+            max_file_size_mb: max_file_size_mb_default(),
+            max_duration_minutes: max_duration_minutes_default()
+        }
+    }
+}
+
+fn max_output_megapixels_default() -> u32 {
+    200
+}
+
+fn max_file_size_mb_default() -> u32 {
+    500
+}
+
+fn max_duration_minutes_default() -> u32 {
+    30
+}
@@ -1,19 +1,7 @@
 use serde::{Serialize, Deserialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, Default)]
 pub struct Misc {
-    #[serde(default = "enable_default")] 
-    pub enable_custom_folder: bool
-}
-
-impl Default for Misc {
-    fn default() -> Self {
-        Self {
-            enable_custom_folder: enable_default()
-        }
-    }
-}
-
-fn enable_default() -> bool {
-    true
+    #[serde(default)]
+    pub auto_preview_output: bool
 }
\ No newline at end of file
@@ -1,2 +1,8 @@
 pub mod key_binds;
-pub mod misc;
\ No newline at end of file
+pub mod misc;
+pub mod limits;
+pub mod workers;
+pub mod backend;
+pub mod defaults;
+pub mod models;
+pub mod theme;
\ No newline at end of file
@@ -0,0 +1,20 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct Models {
+    /// Also load models from the "models" folder in our config folder.
+    #[serde(default = "custom_folder_default")]
+    pub custom_folder: bool
+}
+
+impl Default for Models {
+    fn default() -> Self {
+        Self {
+            custom_folder: custom_folder_default()
+        }
+    }
+}
+
+fn custom_folder_default() -> bool {
+    true
+}
\ No newline at end of file
@@ -0,0 +1,24 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Hash)]
+pub struct ThemeConfig {
+    /// "system", "dark", "light" or the name of a theme file in the "themes" config folder.
+    #[serde(default = "name_default")]
+    pub name: String,
+    /// Hex code (e.g. "#e05f78") for the spinner, drop outline and such, empty keeps the theme's own.
+    #[serde(default)]
+    pub accent_colour: String
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: name_default(),
+            accent_colour: String::new()
+        }
+    }
+}
+
+fn name_default() -> String {
+    "system".to_string()
+}
\ No newline at end of file
@@ -0,0 +1,28 @@
+use serde::{Serialize, Deserialize};
+
+/// How many upscayl-bin processes run at once and what each of them gets to use.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Hash)]
+pub struct Workers {
+    #[serde(default = "concurrency_default")]
+    pub concurrency: u32,
+    /// GPUs handed out to the workers in turn, empty leaves it up to upscayl-bin.
+    #[serde(default)]
+    pub gpu_ids: Vec<i32>,
+    /// Threads shared between the workers, 0 leaves it up to upscayl-bin.
+    #[serde(default)]
+    pub cpu_threads: u32
+}
+
+impl Default for Workers {
+    fn default() -> Self {
+        Self {
+            concurrency: concurrency_default(),
+            gpu_ids: Vec::new(),
+            cpu_threads: 0
+        }
+    }
+}
+
+fn concurrency_default() -> u32 {
+    1
+}
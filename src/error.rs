@@ -2,6 +2,8 @@ use std::{fmt::{self, Display, Formatter}, path::PathBuf};
 
 use cirrus_error::v1::error::CError;
 
+use crate::processing::crop::Crop;
+
 type AE = Option<String>;
 
 #[derive(Debug, Clone)]
@@ -14,7 +16,83 @@ pub enum Error {
     NoModels(AE, PathBuf),
     FailedToInitImage(AE, PathBuf, String),
     ImageFormatNotSupported(AE, String),
-    FailedToGetCurrentExecutablePath(AE)
+    FailedToGetCurrentExecutablePath(AE),
+    InvalidCrop(AE, Crop, String),
+    FailedToCopyMetadata(AE, String),
+    FailedToEncodeImage(AE, String, String),
+    FailedToProcessAlpha(AE, String),
+    FailedToProcessAnimation(AE, String),
+    FailedToTileImage(AE, String),
+    FailedToOpenPath(AE, PathBuf),
+    FailedToBenchmark(AE, String),
+    FailedToCompareModels(AE, String),
+    ModelNotFound(AE, String),
+    FailedToMigrateConfig(AE, PathBuf, String),
+    FailedToLoadConfig(AE, PathBuf, String),
+    FailedToLoadTheme(AE, String, String),
+    PlannedJobsWouldFail(AE, usize)
+}
+
+impl Error {
+    /// The variant name, used as the error kind in JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::FileNotFound(..) => "FileNotFound",
+            Error::NoFileSelected(..) => "NoFileSelected",
+            Error::FailedToUpscaleImage(..) => "FailedToUpscaleImage",
+            Error::UpscaylNotInPath(..) => "UpscaylNotInPath",
+            Error::ModelsFolderNotFound(..) => "ModelsFolderNotFound",
+            Error::NoModels(..) => "NoModels",
+            Error::FailedToInitImage(..) => "FailedToInitImage",
+            Error::ImageFormatNotSupported(..) => "ImageFormatNotSupported",
+            Error::FailedToGetCurrentExecutablePath(..) => "FailedToGetCurrentExecutablePath",
+            Error::InvalidCrop(..) => "InvalidCrop",
+            Error::FailedToCopyMetadata(..) => "FailedToCopyMetadata",
+            Error::FailedToEncodeImage(..) => "FailedToEncodeImage",
+            Error::FailedToProcessAlpha(..) => "FailedToProcessAlpha",
+            Error::FailedToProcessAnimation(..) => "FailedToProcessAnimation",
+            Error::FailedToTileImage(..) => "FailedToTileImage",
+            Error::FailedToOpenPath(..) => "FailedToOpenPath",
+            Error::FailedToBenchmark(..) => "FailedToBenchmark",
+            Error::FailedToCompareModels(..) => "FailedToCompareModels",
+            Error::ModelNotFound(..) => "ModelNotFound",
+            Error::FailedToMigrateConfig(..) => "FailedToMigrateConfig",
+            Error::FailedToLoadConfig(..) => "FailedToLoadConfig",
+            Error::FailedToLoadTheme(..) => "FailedToLoadTheme",
+            Error::PlannedJobsWouldFail(..) => "PlannedJobsWouldFail",
+        }
+    }
+
+    /// The process exit code for headless runs that fail with this error. These are part
+    /// of the CLI's interface (see the README) so existing codes must never change.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::FileNotFound(..) => 10,
+            Error::NoFileSelected(..) => 11,
+            Error::FailedToUpscaleImage(..) => 12,
+            Error::UpscaylNotInPath(..) => 13,
+            Error::ModelsFolderNotFound(..) => 14,
+            Error::NoModels(..) => 15,
+            Error::FailedToInitImage(..) => 16,
+            Error::ImageFormatNotSupported(..) => 17,
+            Error::FailedToGetCurrentExecutablePath(..) => 18,
+            Error::InvalidCrop(..) => 19,
+            Error::FailedToCopyMetadata(..) => 20,
+            Error::FailedToEncodeImage(..) => 21,
+            Error::FailedToProcessAlpha(..) => 22,
+            Error::FailedToProcessAnimation(..) => 23,
+            Error::FailedToTileImage(..) => 24,
+            Error::FailedToOpenPath(..) => 25,
+            Error::FailedToBenchmark(..) => 26,
+            Error::FailedToCompareModels(..) => 27,
+            Error::ModelNotFound(..) => 28,
+            Error::FailedToMigrateConfig(..) => 29,
+            Error::FailedToLoadConfig(..) => 30,
+            Error::FailedToLoadTheme(..) => 31,
+            // the dry run's exit code was 3 before it had an error kind.
+            Error::PlannedJobsWouldFail(..) => 3,
+        }
+    }
 }
 
 impl CError for Error {
@@ -33,6 +111,20 @@ impl CError for Error {
             Error::FailedToInitImage(actual_error, _, _) => actual_error,
             Error::ImageFormatNotSupported(actual_error, _) => actual_error,
             Error::FailedToGetCurrentExecutablePath(actual_error) => actual_error,
+            Error::InvalidCrop(actual_error, _, _) => actual_error,
+            Error::FailedToCopyMetadata(actual_error, _) => actual_error,
+            Error::FailedToEncodeImage(actual_error, _, _) => actual_error,
+            Error::FailedToProcessAlpha(actual_error, _) => actual_error,
+            Error::FailedToProcessAnimation(actual_error, _) => actual_error,
+            Error::FailedToTileImage(actual_error, _) => actual_error,
+            Error::FailedToOpenPath(actual_error, _) => actual_error,
+            Error::FailedToBenchmark(actual_error, _) => actual_error,
+            Error::FailedToCompareModels(actual_error, _) => actual_error,
+            Error::ModelNotFound(actual_error, _) => actual_error,
+            Error::FailedToMigrateConfig(actual_error, _, _) => actual_error,
+            Error::FailedToLoadConfig(actual_error, _, _) => actual_error,
+            Error::FailedToLoadTheme(actual_error, _, _) => actual_error,
+            Error::PlannedJobsWouldFail(actual_error, _) => actual_error,
         }
     }
 }
@@ -79,6 +171,48 @@ impl Display for Error {
             Error::FailedToGetCurrentExecutablePath(_) => write!(
                 f, "Failed to get the current path where aeternum is located."
             ),
+            Error::InvalidCrop(_, crop, detail) => write!(
+                f, "The crop region '{}' is invalid! {}", crop, detail
+            ),
+            Error::FailedToCopyMetadata(_, reason) => write!(
+                f, "Failed to copy the image metadata to the upscaled image. Reason: {}", reason
+            ),
+            Error::FailedToEncodeImage(_, format, reason) => write!(
+                f, "Failed to encode the upscaled image as {}. Reason: {}", format, reason
+            ),
+            Error::FailedToProcessAlpha(_, reason) => write!(
+                f, "Failed to process the alpha channel of the image. Reason: {}", reason
+            ),
+            Error::FailedToProcessAnimation(_, reason) => write!(
+                f, "Failed to upscale the animated image. Reason: {}", reason
+            ),
+            Error::FailedToTileImage(_, reason) => write!(
+                f, "Failed to upscale the image in tiles. Reason: {}", reason
+            ),
+            Error::FailedToOpenPath(_, path) => write!(
+                f, "Failed to open '{}'!", path.display()
+            ),
+            Error::FailedToBenchmark(_, reason) => write!(
+                f, "Failed to benchmark the models. Reason: {}", reason
+            ),
+            Error::FailedToCompareModels(_, reason) => write!(
+                f, "Failed to compare the models. Reason: {}", reason
+            ),
+            Error::ModelNotFound(_, name) => write!(
+                f, "There is no model called '{}'!", name
+            ),
+            Error::FailedToMigrateConfig(_, path, reason) => write!(
+                f, "Failed to upgrade the config at '{}' to the current version. Reason: {}", path.display(), reason
+            ),
+            Error::FailedToLoadConfig(_, path, reason) => write!(
+                f, "Failed to load the config at '{}'! Reason: {}", path.display(), reason
+            ),
+            Error::FailedToLoadTheme(_, name, reason) => write!(
+                f, "Failed to load the theme '{}'! Reason: {}", name, reason
+            ),
+            Error::PlannedJobsWouldFail(_, count) => write!(
+                f, "{} of the planned jobs would fail.", count
+            ),
         }
     }
 }
@@ -1,12 +1,12 @@
-use std::path::PathBuf;
+use std::{path::{Path, PathBuf}, process::Command};
 
 use rfd::FileDialog;
 
-use crate::{image::Image, Error};
+use crate::{formats, image::Image, Error};
 
 pub fn select_image() -> Result<Image, Error> {
     let image_path = FileDialog::new()
-        .add_filter("images", &["png", "jpeg", "jpg", "webp"])
+        .add_filter("images", &formats::input_extensions())
         .pick_file();
 
     let image_or_error = match image_path {
@@ -36,4 +36,40 @@ pub fn save_folder() -> Result<PathBuf, Error> {
         },
         None => Err(Error::NoFileSelected(None))
     }
-}
\ No newline at end of file
+}
+
+/// Opens the file or folder at `path` with the system's default application.
+pub fn open_path(path: &Path) -> Result<(), Error> {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+
+    spawn(Command::new(program).arg(path), path)
+}
+
+/// Opens the file manager at the folder containing `path`, selecting it where the platform allows.
+pub fn reveal_in_folder(path: &Path) -> Result<(), Error> {
+    if cfg!(target_os = "windows") {
+        return spawn(Command::new("explorer").arg(format!("/select,{}", path.display())), path);
+    }
+
+    if cfg!(target_os = "macos") {
+        return spawn(Command::new("open").arg("-R").arg(path), path);
+    }
+
+    match path.parent() {
+        Some(folder) => open_path(folder),
+        None => Err(Error::FailedToOpenPath(Some("Path has no parent folder.".to_string()), path.to_path_buf()))
+    }
+}
+
+fn spawn(command: &mut Command, path: &Path) -> Result<(), Error> {
+    match command.spawn() {
+        Ok(_) => Ok(()),
+        Err(error) => Err(Error::FailedToOpenPath(Some(error.to_string()), path.to_path_buf()))
+    }
+}
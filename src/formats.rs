@@ -0,0 +1,96 @@
+use std::{fs::File, io::Read, path::Path};
+
+use image::ImageFormat;
+
+use crate::error::Error;
+
+/// How we get pixels out of an input format that upscayl-bin can work with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decoder {
+    /// upscayl-bin reads these directly.
+    Native,
+    /// Decoded by the `image` crate and converted to a temporary PNG.
+    Image(ImageFormat),
+    /// Converted to a temporary PNG by an external tool in the path.
+    External(&'static str)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputFormat {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub decoder: Decoder
+}
+
+/// Every image format aeternum accepts as input.
+pub static INPUT_FORMATS: &[InputFormat] = &[
+    InputFormat { name: "PNG", extensions: &["png"], decoder: Decoder::Native },
+    InputFormat { name: "JPEG", extensions: &["jpg", "jpeg"], decoder: Decoder::Native },
+    InputFormat { name: "WebP", extensions: &["webp"], decoder: Decoder::Native },
+    InputFormat { name: "TIFF", extensions: &["tif", "tiff"], decoder: Decoder::Image(ImageFormat::Tiff) },
+    InputFormat { name: "BMP", extensions: &["bmp"], decoder: Decoder::Image(ImageFormat::Bmp) },
+    InputFormat { name: "GIF", extensions: &["gif"], decoder: Decoder::Image(ImageFormat::Gif) },
+    // decoding AVIF needs dav1d so it's behind the "avif" feature.
+    #[cfg(feature = "avif")]
+    InputFormat { name: "AVIF", extensions: &["avif"], decoder: Decoder::Image(ImageFormat::Avif) },
+    InputFormat { name: "HEIC", extensions: &["heic", "heif"], decoder: Decoder::External("heif-convert") },
+];
+
+/// All extensions of the supported input formats (e.g. for file dialogue filters).
+pub fn input_extensions() -> Vec<&'static str> {
+    INPUT_FORMATS.iter()
+        .flat_map(|format| format.extensions.iter().copied())
+        .collect()
+}
+
+/// Detects the format of an image from its content rather
+/// than trusting the file extension.
+pub fn sniff(path: &Path) -> Result<&'static InputFormat, Error> {
+    let mut header = [0u8; 32];
+
+    let read = match File::open(path).and_then(|mut file| file.read(&mut header)) {
+        Ok(read) => read,
+        Err(error) => return Err(
+            Error::FailedToInitImage(
+                Some(error.to_string()),
+                path.to_path_buf(),
+                "Failed to read the image header.".to_string()
+            )
+        )
+    };
+
+    let header = &header[..read];
+
+    let name = if is_heif(header) {
+        Some("HEIC")
+    } else {
+        match image::guess_format(header) {
+            Ok(ImageFormat::Png) => Some("PNG"),
+            Ok(ImageFormat::Jpeg) => Some("JPEG"),
+            Ok(ImageFormat::WebP) => Some("WebP"),
+            Ok(ImageFormat::Tiff) => Some("TIFF"),
+            Ok(ImageFormat::Bmp) => Some("BMP"),
+            Ok(ImageFormat::Gif) => Some("GIF"),
+            Ok(ImageFormat::Avif) => Some("AVIF"),
+            _ => None
+        }
+    };
+
+    match name.and_then(|name| INPUT_FORMATS.iter().find(|format| format.name == name)) {
+        Some(format) => Ok(format),
+        None => {
+            let extension = path.extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+
+            Err(Error::ImageFormatNotSupported(None, extension))
+        }
+    }
+}
+
+fn is_heif(header: &[u8]) -> bool {
+    header.get(4..8) == Some(&b"ftyp"[..]) && matches!(
+        header.get(8..12),
+        Some(b"heic" | b"heix" | b"heim" | b"heis" | b"hevc" | b"hevx")
+    )
+}
@@ -0,0 +1,411 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use serde_json::json;
+
+use crate::{config::{config::Config, layers::ConfigLayers}, error::Error, image::Image, plan::{self, PlannedJob}, processing::crop::Crop, scheduler::JobState, upscale::{Backend, BackendKind, OutputExt, Progress, Stage, Upscale, UpscaleOptions}};
+
+#[derive(clap::Args, Debug)]
+pub struct HeadlessArgs {
+    /// Upscale the image without opening a window, see the README for exit codes.
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Print one JSON event per line instead of plain text (headless only).
+    #[arg(long, requires = "headless")]
+    pub json: bool,
+
+    /// Model to upscale with, defaults to the first one found (headless only).
+    #[arg(short, long, requires = "headless")]
+    pub model: Option<String>,
+
+    /// How many times bigger the output should be (headless only).
+    #[arg(short, long, requires = "headless")]
+    pub scale: Option<i8>,
+
+    /// Output format: WebP, PNG, JPG, AVIF, TIFF or GIF (headless only).
+    #[arg(long, requires = "headless")]
+    pub format: Option<OutputExt>,
+
+    /// Folder to write the output to, defaults to the image's folder (headless only).
+    #[arg(short, long, requires = "headless")]
+    pub output: Option<PathBuf>,
+
+    /// Print what would be upscaled and where without running upscayl-bin (headless only).
+    #[arg(long, requires = "headless")]
+    pub dry_run: bool,
+
+    /// Finish the batch the last run didn't get to, before any images given (headless only).
+    #[arg(long, requires = "headless", conflicts_with = "dry_run")]
+    pub resume: bool,
+}
+
+/// Prints job events to stdout, as JSON lines or plain text.
+#[derive(Clone)]
+struct Reporter {
+    json: bool,
+    input: String
+}
+
+impl Reporter {
+    fn queued(&self, output: &Path, options: &UpscaleOptions) {
+        if self.json {
+            println!(
+                "{}",
+                json!({
+                    "event": "queued",
+                    "input": self.input,
+                    "output": output,
+                    "model": options.model.as_ref().map(|model| &model.name),
+                    "scale": options.scale
+                })
+            );
+        }
+    }
+
+    fn started(&self, backend: &Backend) {
+        match self.json {
+            true => println!(
+                "{}",
+                json!({
+                    "event": "started",
+                    "input": self.input,
+                    "worker": backend.slot,
+                    "gpu_id": backend.gpu_id
+                })
+            ),
+            false => eprintln!("[{}] Started on {}.", self.input, backend.label())
+        }
+    }
+
+    fn finished(&self, output: &Path, options: &UpscaleOptions, duration_secs: f64) {
+        match self.json {
+            true => println!(
+                "{}",
+                json!({
+                    "event": "finished",
+                    "input": self.input,
+                    "output": output,
+                    "model": options.model.as_ref().map(|model| &model.name),
+                    "scale": options.scale,
+                    "duration_secs": duration_secs
+                })
+            ),
+            false => println!("{}", output.display())
+        }
+    }
+
+    fn error(&self, error: &Error, duration_secs: f64) {
+        match self.json {
+            true => println!(
+                "{}",
+                json!({
+                    "event": "error",
+                    "input": self.input,
+                    "duration_secs": duration_secs,
+                    "kind": error.kind(),
+                    "message": error.to_string(),
+                    "exit_code": error.exit_code()
+                })
+            ),
+            false => eprintln!("[{}] {}", self.input, error)
+        }
+    }
+
+    fn skipped(&self) {
+        match self.json {
+            true => println!("{}", json!({"event": "skipped", "input": self.input})),
+            false => eprintln!("[{}] Skipped, it was already upscaled.", self.input)
+        }
+    }
+}
+
+impl Progress for Reporter {
+    fn stage(&self, _stage: Stage, message: String) {
+        match self.json {
+            true => println!("{}", json!({"event": "progress", "input": self.input, "message": message})),
+            false => eprintln!("[{}] {}", self.input, message)
+        }
+    }
+
+    fn warning(&self, error: Error) {
+        match self.json {
+            true => println!(
+                "{}",
+                json!({"event": "warning", "input": self.input, "kind": error.kind(), "message": error.to_string()})
+            ),
+            false => eprintln!("[{}] Warning: {}", self.input, error)
+        }
+    }
+}
+
+/// A job we're waiting on, `index` is where its exit code goes.
+struct QueuedJob {
+    index: usize,
+    reporter: Reporter,
+    options: UpscaleOptions,
+    started: bool
+}
+
+/// Upscales every image in `image_paths` without the GUI (or only plans it on a dry run),
+/// returning the process exit code. Each image gets its options from `config` with the
+/// `layers` for that image (its project config and `--config`) merged over it.
+pub fn run(
+    args: &HeadlessArgs,
+    image_paths: &[String],
+    crop: Option<Crop>,
+    backend: BackendKind,
+    config: &Config,
+    mut layers: ConfigLayers
+) -> i32 {
+    let mut upscale = match setup(backend, &layers.apply(config)) {
+        Ok(upscale) => upscale,
+        Err(error) => {
+            let reporter = Reporter {
+                json: args.json,
+                input: String::new()
+            };
+
+            reporter.error(&error, 0.0);
+            return error.exit_code();
+        }
+    };
+
+    let inputs: Vec<PathBuf> = image_paths.iter().map(PathBuf::from).collect();
+
+    if args.dry_run {
+        let mut planned = Vec::with_capacity(inputs.len());
+        let mut config_errors = Vec::new();
+
+        for (index, input) in inputs.into_iter().enumerate() {
+            if let Err(error) = use_image_config(&mut upscale, args, config, &mut layers, &input) {
+                config_errors.push((index, error));
+            }
+
+            upscale.options.crop = crop;
+            planned.push((input, upscale.options.clone()));
+        }
+
+        let mut jobs = plan::plan(&planned);
+
+        for (index, error) in config_errors {
+            jobs[index].output = None;
+            jobs[index].errors.push(error.to_string());
+        }
+
+        return print_plan(args.json, &jobs);
+    }
+
+    let unfinished = upscale.session.lock().unwrap().unfinished();
+
+    if !args.resume && !unfinished.is_empty() {
+        eprintln!(
+            "{} image(s) were left over from a batch that didn't finish, pass --resume to upscale them.",
+            unfinished.len()
+        );
+    }
+
+    let resumed = match args.resume {
+        true => unfinished,
+        false => Vec::new()
+    };
+
+    // resumed items come first in the exit codes, then the images we were given.
+    let offset = resumed.len();
+    let mut exit_codes = vec![0; offset + inputs.len()];
+    let mut jobs: HashMap<usize, QueuedJob> = HashMap::new();
+
+    for (index, item) in resumed.into_iter().enumerate() {
+        let reporter = Reporter {
+            json: args.json,
+            input: item.input.display().to_string()
+        };
+
+        reporter.queued(&item.output, &item.options);
+
+        let options = item.options.clone();
+
+        match upscale.requeue(item, Box::new(reporter.clone())) {
+            Ok(id) => {
+                jobs.insert(id, QueuedJob { index, reporter, options, started: false });
+            },
+            Err(error) => {
+                reporter.error(&error, 0.0);
+                exit_codes[index] = error.exit_code();
+            }
+        }
+    }
+
+    for (index, input) in inputs.into_iter().enumerate() {
+        let index = offset + index;
+
+        let reporter = Reporter {
+            json: args.json,
+            input: input.display().to_string()
+        };
+
+        let prepared = use_image_config(&mut upscale, args, config, &mut layers, &input)
+            .and_then(|_| prepare_image(&mut upscale, input, crop));
+
+        match prepared {
+            Ok(image) => {
+                reporter.queued(&upscale.output_path(&image), &upscale.options);
+
+                let id = upscale.queue(image, Box::new(reporter.clone()));
+                let options = upscale.options.clone();
+
+                jobs.insert(id, QueuedJob { index, reporter, options, started: false });
+            },
+            Err(error) => {
+                reporter.error(&error, 0.0);
+                exit_codes[index] = error.exit_code();
+            }
+        }
+    }
+
+    while let Some(event) = upscale.next_event() {
+        let duration_secs = upscale.job(event.id)
+            .and_then(|job| job.started_at)
+            .map_or(0.0, |started_at| started_at.elapsed().as_secs_f64());
+
+        let job = match jobs.get_mut(&event.id) {
+            Some(job) => job,
+            None => continue
+        };
+
+        match (event.state, &event.backend) {
+            (JobState::Preparing, Some(backend)) if !job.started => {
+                job.started = true;
+                job.reporter.started(backend);
+            },
+            (JobState::Succeeded { output }, _) => job.reporter.finished(&output, &job.options, duration_secs),
+            (JobState::Skipped { .. }, _) => job.reporter.skipped(),
+            (JobState::Failed { error }, _) => {
+                job.reporter.error(&error, duration_secs);
+                exit_codes[job.index] = error.exit_code();
+            },
+            _ => {}
+        }
+    }
+
+    // the batch carries on but the first failure decides the exit code.
+    exit_codes.into_iter()
+        .find(|code| *code != 0)
+        .unwrap_or(0)
+}
+
+/// Prints the plan, returning `PlannedJobsWouldFail`'s exit code if any of the jobs would fail.
+fn print_plan(json: bool, jobs: &[PlannedJob]) -> i32 {
+    for job in jobs {
+        if json {
+            println!("{}", json!({"event": "plan", "job": job}));
+            continue;
+        }
+
+        let output = match &job.output {
+            Some(output) => output.display().to_string(),
+            None => "?".to_string()
+        };
+
+        println!(
+            "{} -> {} ({}, x{})",
+            job.input.display(),
+            output,
+            job.model.as_deref().unwrap_or("no model"),
+            job.scale
+        );
+
+        for error in job.errors.iter() {
+            println!("    error: {}", error);
+        }
+
+        for warning in job.warnings.iter() {
+            println!("    warning: {}", warning);
+        }
+    }
+
+    let failing = jobs.iter().filter(|job| !job.runnable()).count();
+
+    if failing == 0 {
+        return 0;
+    }
+
+    let error = Error::PlannedJobsWouldFail(None, failing);
+
+    Reporter { json, input: String::new() }.error(&error, 0.0);
+
+    error.exit_code()
+}
+
+/// The backend, models and workers are shared by the whole batch so they come from
+/// the first image's config, only the upscale options can change from image to image.
+fn setup(backend: BackendKind, config: &Config) -> Result<Upscale, Error> {
+    let mut upscale = match backend {
+        BackendKind::Upscayl => Upscale::new(&config.backend)?,
+        BackendKind::Mock => Upscale::mock()
+    };
+
+    upscale.init(config.models.custom_folder)?;
+    upscale.set_workers(&config.workers);
+
+    Ok(upscale)
+}
+
+/// Sets the options from the config that applies to `image_path` with the command line's over them.
+fn use_image_config(
+    upscale: &mut Upscale,
+    args: &HeadlessArgs,
+    config: &Config,
+    layers: &mut ConfigLayers,
+    image_path: &Path
+) -> Result<(), Error> {
+    layers.set_image(image_path)?;
+
+    let config = layers.apply(config);
+    let defaults_result = upscale.apply_defaults(&config.defaults);
+
+    let model = match (&args.model, &upscale.options.model) {
+        (Some(name), _) => upscale.models.iter()
+            .find(|model| model.name == *name)
+            .cloned()
+            .ok_or(Error::ModelNotFound(None, name.clone()))?,
+        (None, Some(model)) => model.clone(),
+        (None, None) => {
+            // a default model that doesn't exist is only a problem if we'd have used it.
+            defaults_result?;
+            upscale.models[0].clone()
+        }
+    };
+
+    upscale.options.model = Some(model);
+
+    if let Some(scale) = args.scale {
+        upscale.options.scale = scale;
+    }
+
+    if let Some(format) = &args.format {
+        upscale.options.output_ext = format.clone();
+    }
+
+    if let Some(output) = &args.output {
+        upscale.options.output = Some(output.clone());
+    }
+
+    Ok(())
+}
+
+fn prepare_image(upscale: &mut Upscale, path: PathBuf, crop: Option<Crop>) -> Result<Image, Error> {
+    if !path.exists() {
+        return Err(Error::FileNotFound(None, path, "That file doesn't exist!".to_string()));
+    }
+
+    let image = Image::from_path(path)?;
+
+    upscale.options.crop = None;
+
+    if let Some(crop) = crop {
+        crop.validate(&image.image_size)?;
+        upscale.options.crop = Some(crop);
+    }
+
+    Ok(image)
+}
@@ -0,0 +1,120 @@
+use std::{fs, path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use cirrus_path::v1::get_user_config_dir_path;
+use serde::{Deserialize, Serialize};
+
+use crate::{upscale::UpscaleOptions, APP_NAME};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JobStatus {
+    Succeeded,
+    Failed(String)
+}
+
+/// A finished (or failed) upscale, kept so it can be found and re-ran later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// Seconds since the unix epoch of when the job finished.
+    pub finished_at: u64,
+    pub duration_secs: f64,
+    pub status: JobStatus,
+    pub backend_version: String,
+    pub options: UpscaleOptions
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default)]
+    entries: Vec<HistoryEntry>
+}
+
+/// How many entries are kept, the oldest are dropped past this.
+pub const MAX_ENTRIES: usize = 500;
+
+/// Every upscale job, stored in "history.toml" in the user's config folder.
+#[derive(Debug, Default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    /// Bumped whenever the entries change so the window knows to refresh its copy.
+    pub revision: u64,
+    path: Option<PathBuf>
+}
+
+impl History {
+    pub fn load() -> Self {
+        let path = match get_user_config_dir_path(APP_NAME) {
+            Ok(config_dir_path) => config_dir_path.join("history.toml"),
+            Err(error) => {
+                log::warn!("Failed to get config path, history won't be saved! Error: {}", error.human_message());
+                return Self::default();
+            }
+        };
+
+        let entries = match fs::read_to_string(&path) {
+            Ok(string) => match toml::from_str::<HistoryFile>(&string) {
+                Ok(history_file) => history_file.entries,
+                Err(error) => {
+                    log::warn!("Failed to parse history file, starting a new one! Error: {}", error);
+                    Vec::new()
+                }
+            },
+            Err(_) => Vec::new()
+        };
+
+        let mut history = Self {
+            entries,
+            revision: 0,
+            path: Some(path)
+        };
+
+        history.truncate();
+
+        history
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        self.truncate();
+        self.revision += 1;
+        self.save();
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.revision += 1;
+        self.save();
+    }
+
+    fn truncate(&mut self) {
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return
+        };
+
+        let history_file = HistoryFile {
+            entries: self.entries.clone()
+        };
+
+        let result = toml::to_string(&history_file)
+            .map_err(|error| error.to_string())
+            .and_then(|string| fs::write(path, string).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            log::error!("Failed to save history to '{}'! Error: {}", path.display(), error);
+        }
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
@@ -1,55 +1,77 @@
-use std::path::PathBuf;
+use std::{path::{Path, PathBuf}, sync::Arc};
 use imagesize::ImageSize;
 
-use crate::{upscale::UpscaleOptions, Error};
+use ::image::metadata::Orientation;
+
+use crate::{formats::{self, Decoder}, processing::{animation, convert, orientation, TempFile}, upscale::UpscaleOptions, Error};
 
 #[derive(Clone)]
 pub struct Image {
     pub path: PathBuf,
+    /// The file we actually read pixels from, this differs from `path` when the 
+    /// image had to be converted to PNG or rotated upright from its EXIF orientation.
+    pub input_path: PathBuf,
+    /// Keeps the converted copy at `input_path` around until every job using the image is done with it.
+    _converted: Option<Arc<TempFile>>,
+    /// Animated GIFs and WebPs get upscaled frame by frame from `path`.
+    pub animated: bool,
     pub image_size: ImageSize
 }
 
 impl Image {
     pub fn from_path(path: PathBuf) -> Result<Self, Error> {
-        if let Some(extension) = path.extension() {
-            let allowed_extensions = vec!["png", "jpeg", "jpg", "webp"];
-            let extension_string = extension.to_string_lossy().to_string().to_lowercase();
-
-            match allowed_extensions.iter().any(|e| extension_string.contains(e)) {
-                true => {
-                    let image_size = match imagesize::size(&path) {
-                        Ok(size) => size,
-                        Err(why) => return Err(
-                            Error::FailedToInitImage(
-                                Some(why.to_string()), 
-                                path.clone(), 
-                                "Failed to get image size.".to_string()
-                            )
-                        )
-                    };
-
-                    Ok(Self {
-                        path,
-                        image_size
-                    })
-                },
-                false => Err(Error::ImageFormatNotSupported(None, extension_string.clone())),
-            }
-        } else {
-            Err(Error::ImageFormatNotSupported(None, "".to_string()))
-        }
-    }
+        let format = formats::sniff(&path)?;
+
+        let orientation = orientation::read_orientation(&path);
+
+        let animated = matches!(format.name, "GIF" | "WebP") && animation::is_animated(&path);
+
+        let converted = match (format.decoder, orientation) {
+            _ if animated => None,
+            (Decoder::Native, Orientation::NoTransforms) => None,
+            _ => Some(Arc::new(TempFile(convert::to_png(&path, format, orientation)?)))
+        };
 
-    pub fn create_output(&self, options: &UpscaleOptions) -> String {
-        let model = &options.model.clone().unwrap();
-        let extension = &options.output_ext.to_string().to_lowercase();
-
-        format!(
-            "{}_{}_x{}.{}", 
-            self.path.file_stem().unwrap().to_string_lossy(), 
-            model.name, 
-            &options.scale,
-            extension
-        )
+        let input_path = match &converted {
+            Some(converted) => converted.0.clone(),
+            None => path.clone()
+        };
+
+        // we measure the file we'll actually upscale so rotated images aren't transposed.
+        let image_size = match imagesize::size(&input_path) {
+            Ok(size) => size,
+            Err(why) => return Err(
+                Error::FailedToInitImage(
+                    Some(why.to_string()), 
+                    path.clone(), 
+                    "Failed to get image size.".to_string()
+                )
+            )
+        };
+
+        Ok(Self {
+            path,
+            input_path,
+            _converted: converted,
+            animated,
+            image_size
+        })
     }
+}
+
+/// The file name the upscale of the image at `path` gets written to.
+pub fn output_name(path: &Path, animated: bool, options: &UpscaleOptions) -> String {
+    let model = &options.model.clone().unwrap();
+    let extension = match animated {
+        true => animation::output_ext(&options.output_ext),
+        false => options.output_ext.clone()
+    }.to_string().to_lowercase();
+
+    format!(
+        "{}_{}_x{}.{}", 
+        path.file_stem().unwrap().to_string_lossy(), 
+        model.name, 
+        &options.scale,
+        extension
+    )
 }
\ No newline at end of file
@@ -1,20 +1,24 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use std::{env, fs, path::PathBuf, time::Duration};
+use std::{env, fs, path::{Path, PathBuf}, process, time::Duration};
 
 use app::Aeternum;
 use cirrus_path::v1::{get_user_config_dir_path};
 use image::Image;
 use log::debug;
-use eframe::egui::{self, Style};
+use eframe::egui;
 use egui_notify::ToastLevel;
 use cirrus_theming::v1::Theme;
-use cirrus_egui::v1::{config_manager::ConfigManager, notifier::Notifier, styling::Styling};
-use clap::{arg, command, Parser};
+use cirrus_egui::v1::{config_manager::ConfigManager, notifier::Notifier};
+use bench::BenchArgs;
+use headless::HeadlessArgs;
+use clap::{arg, command, error::ErrorKind, CommandFactory, Parser, Subcommand};
 use error::Error;
+use processing::crop::Crop;
+use theme::Theming;
 
-use config::config::Config;
-use upscale::Upscale;
+use config::{config::Config, layers::ConfigLayers, migrate};
+use upscale::{BackendKind, Upscale};
 
 mod error;
 mod app;
@@ -23,20 +27,64 @@ mod windows;
 mod files;
 mod upscale;
 mod config;
+mod processing;
+mod formats;
+mod history;
+mod stats;
+mod bench;
+mod compare;
+mod headless;
+mod plan;
+mod scheduler;
+mod session;
+mod mock;
+mod shortcuts;
+mod theme;
+
+#[cfg(test)]
+mod test_utils;
 
 static APP_NAME: &str = "aeternum";
 static TEMPLATE_CONFIG_TOML_STRING: &str = include_str!("../assets/config.template.toml");
 
 #[derive(Parser, Debug)]
 #[clap(author = "Ananas")]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Args {
-    /// Valid path to image.
-    image: Option<String>,
+    #[command(subcommand)]
+    command: Option<Commands>,
 
-    /// Valid themes at the moment: dark, light
+    /// Valid path to image. Headless runs can be given several.
+    images: Vec<String>,
+
+    /// Theme to use over the config's: system, dark, light or the name of a theme file.
     #[arg(short, long)]
     theme: Option<String>,
+
+    /// Only upscale this region of the image, formatted as: x,y,w,h
+    #[arg(long)]
+    crop: Option<Crop>,
+
+    /// What upscales the images, "mock" resamples them instead for testing without a GPU.
+    #[arg(long, value_enum, default_value_t)]
+    backend: BackendKind,
+
+    /// Config file merged over your config and any aeternum.toml found next to the image.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print the config that would be used and where each option was set, then exit.
+    #[arg(long)]
+    print_config: bool,
+
+    #[command(flatten)]
+    headless: HeadlessArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Times every model at several tile sizes on this machine.
+    Bench(BenchArgs),
 }
 
 fn main() -> eframe::Result {
@@ -57,7 +105,57 @@ fn main() -> eframe::Result {
 
     let cli_args = Args::parse();
 
-    let image_path = cli_args.image;
+    // neither of these open a window.
+    if cli_args.headless.headless || cli_args.print_config {
+        if cli_args.headless.headless && cli_args.images.is_empty() && !cli_args.headless.resume {
+            Args::command().error(
+                ErrorKind::MissingRequiredArgument,
+                "--headless needs at least one image (or --resume)."
+            ).exit();
+        }
+
+        match migrate::migrate_user_config() {
+            Ok(report) => {
+                report.notes().iter().for_each(|note| log::info!("{}", note));
+                report.warnings().iter().for_each(|warning| log::warn!("{}", warning));
+            },
+            Err(error) => log::warn!("{}", error)
+        }
+
+        let config_manager: ConfigManager<Config> = match ConfigManager::new(APP_NAME, TEMPLATE_CONFIG_TOML_STRING) {
+            Ok(config) => config,
+            Err(error) => {
+                log::warn!("Failed to initialize config, using the defaults! Error: {}", error.human_message());
+                ConfigManager::default()
+            }
+        };
+
+        let config_layers = match ConfigLayers::load(cli_args.config.as_deref(), cli_args.images.first().map(Path::new)) {
+            Ok(config_layers) => config_layers,
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(error.exit_code());
+            }
+        };
+
+        if cli_args.print_config {
+            print!("{}", config_layers.describe(&config_manager.config));
+            process::exit(0);
+        }
+
+        process::exit(
+            headless::run(
+                &cli_args.headless,
+                &cli_args.images,
+                cli_args.crop,
+                cli_args.backend,
+                &config_manager.config,
+                config_layers
+            )
+        );
+    }
+
+    let image_path = cli_args.images.first().cloned();
     let theme_string = cli_args.theme;
 
     if image_path.is_some() {
@@ -102,28 +200,34 @@ fn main() -> eframe::Result {
         None => None
     };
 
-    let is_dark = match theme_string {
-        Some(string) => {
-            if string == "light" {
-                false
-            } else if string == "dark" {
-                true
-            } else {
-                log::warn!(
-                    "'{}' is not a valid theme. Pass either 'dark' or 'light'.", string
-                );
+    match migrate::migrate_user_config() {
+        Ok(report) => {
+            for note in report.notes() {
+                notifier.toast(note, ToastLevel::Info, |_| {});
+            }
 
-                true
+            let warnings = report.warnings();
+
+            if !warnings.is_empty() {
+                notifier.toast(
+                    format!("There's a problem with your config!\n{}", warnings.join("\n")),
+                    ToastLevel::Warning,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(10)));
+                    }
+                );
             }
         },
-        _ => true
-    };
-
-    let theme = Theme::new(
-        is_dark,
-        vec![],
-        None
-    );
+        Err(error) => {
+            notifier.toast(
+                Box::new(error),
+                ToastLevel::Error,
+                |toast| {
+                    toast.duration(Some(Duration::from_secs(10)));
+                }
+            );
+        }
+    }
 
     let config_manager: ConfigManager<Config> = match ConfigManager::new(APP_NAME, TEMPLATE_CONFIG_TOML_STRING) {
         Ok(config) => config,
@@ -173,7 +277,49 @@ fn main() -> eframe::Result {
         },
     };
 
-    let mut upscale = match Upscale::new() {
+    let config_layers = match ConfigLayers::load(cli_args.config.as_deref(), image.as_ref().map(|image| image.path.as_path())) {
+        Ok(config_layers) => config_layers,
+        Err(error) => {
+            notifier.toast(
+                Box::new(error),
+                ToastLevel::Error,
+                |toast| {
+                    toast.duration(Some(Duration::from_secs(10)));
+                }
+            );
+
+            ConfigLayers::default()
+        }
+    };
+
+    let config = config_layers.apply(&config_manager.config);
+
+    let theme_name = theme_string.unwrap_or_else(|| config.theme.name.clone());
+
+    let theming = match Theming::load(&theme_name, &config.theme) {
+        Ok(theming) => theming,
+        Err(error) => {
+            notifier.toast(
+                Box::new(error),
+                ToastLevel::Error,
+                |toast| {
+                    toast.duration(Some(Duration::from_secs(10)));
+                }
+            );
+
+            Theming::default()
+        }
+    };
+
+    // we don't know the OS's preference until the first frame, the app switches over then if it has to.
+    let theme = theming.theme(None);
+
+    let upscale_result = match cli_args.backend {
+        BackendKind::Upscayl => Upscale::new(&config.backend),
+        BackendKind::Mock => Ok(Upscale::mock())
+    };
+
+    let mut upscale = match upscale_result {
         Ok(upscale) => upscale,
         Err(error) => {
             notifier.toast(
@@ -186,7 +332,7 @@ fn main() -> eframe::Result {
         }
     };
 
-    match upscale.init(config_manager.config.misc.enable_custom_folder) {
+    match upscale.init(config.models.custom_folder) {
         Ok(_) => {},
         Err(error) => {
             notifier.toast(
@@ -199,25 +345,68 @@ fn main() -> eframe::Result {
         }
     }
 
+    upscale.set_workers(&config.workers);
+
+    if let Err(error) = upscale.apply_defaults(&config.defaults) {
+        notifier.toast(
+            Box::new(error),
+            ToastLevel::Warning,
+            |_| {}
+        );
+    }
+
+    if let Some(Commands::Bench(bench_args)) = &cli_args.command {
+        match bench::run(bench_args, &mut upscale) {
+            Ok(results) => {
+                bench::print(bench_args, &results);
+                process::exit(0);
+            },
+            Err(error) => {
+                eprintln!("{}", error);
+                process::exit(1);
+            }
+        }
+    }
+
+    if let Some(crop) = cli_args.crop {
+        let crop_result = match &image {
+            Some(image) => crop.validate(&image.image_size),
+            None => Err(
+                Error::InvalidCrop(None, crop, "No image was given to crop.".to_string())
+            )
+        };
+
+        match crop_result {
+            Ok(_) => upscale.options.crop = Some(crop),
+            Err(error) => {
+                notifier.toast(
+                    Box::new(error),
+                    ToastLevel::Error,
+                    |toast| {
+                        toast.duration(Some(Duration::from_secs(10)));
+                    }
+                );
+            }
+        }
+    }
+
     eframe::run_native(
         "Aeternum",
         options,
         Box::new(|cc| {
-            egui_extras::install_image_loaders(&cc.egui_ctx);
-
-            let mut custom_style = Style {..Default::default()};
-
-            custom_style.spacing.slider_width = 180.0;
-
-            Styling::new(&theme, Some(custom_style))
-                .set_all()
-                .apply(&cc.egui_ctx);
+            setup_context(&cc.egui_ctx, &theme);
 
             Ok(
                 Box::new(
-                    Aeternum::new(image, theme, notifier, upscale, config_manager)
+                    Aeternum::new(image, theme, theming, notifier, upscale, config_manager, config_layers)
                 )
             )
         }),
     )
+}
+
+/// Installs the image loaders and styling the app is laid out with.
+fn setup_context(ctx: &egui::Context, theme: &Theme) {
+    egui_extras::install_image_loaders(ctx);
+    theme::apply(ctx, theme);
 }
\ No newline at end of file
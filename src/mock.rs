@@ -0,0 +1,39 @@
+use std::path::Path;
+
+use image::imageops::FilterType;
+
+use crate::error::Error;
+
+/// Models the mock backend pretends to have, "mock-fail" always fails the way upscayl-bin would.
+pub const MODELS: [&str; 2] = ["mock-x4", "mock-fail"];
+
+/// Stands in for upscayl-bin by resampling `input` to `output`, handing `on_line` the
+/// same kind of lines upscayl-bin prints to stderr while it works.
+pub fn upscale(input: &Path, output: &Path, model: &str, scale: i8, on_line: &mut dyn FnMut(&str)) -> Result<(), Error> {
+    // upscayl-bin starts off with a line about the GPU it picked.
+    on_line("[0 Mock GPU]  queueC=0[1]  queueG=0[1]  queueT=0[1]");
+
+    if model == "mock-fail" {
+        on_line("vkQueueSubmit failed -4");
+
+        return Err(
+            Error::FailedToUpscaleImage(None, "Process returned as not successful.".to_string())
+        );
+    }
+
+    let image = image::open(input).map_err(
+        |error| Error::FailedToUpscaleImage(Some(error.to_string()), "Failed to read the input.".to_string())
+    )?;
+
+    for step in 0..=4 {
+        on_line(&format!("{:.2}%", step as f32 * 25.0));
+    }
+
+    let scale = scale.max(1) as u32;
+
+    image.resize_exact(image.width() * scale, image.height() * scale, FilterType::CatmullRom)
+        .save(output)
+        .map_err(
+            |error| Error::FailedToUpscaleImage(Some(error.to_string()), "Failed to write the output.".to_string())
+        )
+}
@@ -0,0 +1,114 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::Serialize;
+
+use crate::{formats::{self, Decoder}, processing::{animation, orientation}, upscale::{self, UpscaleOptions}};
+
+/// What would happen to one input if the job ran, without touching upscayl-bin.
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedJob {
+    pub input: PathBuf,
+    pub output: Option<PathBuf>,
+    pub model: Option<String>,
+    pub scale: i8,
+    /// Problems that would make the job fail, it'll be skipped if there are any.
+    pub errors: Vec<String>,
+    /// Things worth knowing that won't stop the job, like overwriting a file.
+    pub warnings: Vec<String>
+}
+
+impl PlannedJob {
+    pub fn runnable(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// Resolves every input to the output it would be written to with its options, flagging
+/// unsupported files and outputs that collide with each other or existing files.
+pub fn plan(inputs: &[(PathBuf, UpscaleOptions)]) -> Vec<PlannedJob> {
+    let mut jobs: Vec<PlannedJob> = inputs.iter()
+        .map(|(input, options)| plan_job(input.clone(), options))
+        .collect();
+
+    let mut outputs: HashMap<PathBuf, usize> = HashMap::new();
+
+    for index in 0..jobs.len() {
+        let output = match &jobs[index].output {
+            Some(output) => output.clone(),
+            None => continue
+        };
+
+        match outputs.get(&output) {
+            Some(first) => {
+                let first_input = jobs[*first].input.display().to_string();
+
+                jobs[index].errors.push(format!("Writes to the same output as '{}'.", first_input));
+            },
+            None => {
+                outputs.insert(output.clone(), index);
+
+                if output.exists() {
+                    jobs[index].warnings.push("The output already exists and will be overwritten.".to_string());
+                }
+            }
+        }
+    }
+
+    jobs
+}
+
+fn plan_job(input: PathBuf, options: &UpscaleOptions) -> PlannedJob {
+    let mut job = PlannedJob {
+        input: input.clone(),
+        output: None,
+        model: options.model.as_ref().map(|model| model.name.clone()),
+        scale: options.scale,
+        errors: Vec::new(),
+        warnings: Vec::new()
+    };
+
+    if job.model.is_none() {
+        job.errors.push("No model selected.".to_string());
+    }
+
+    if !input.exists() {
+        job.errors.push("The file doesn't exist.".to_string());
+        return job;
+    }
+
+    let format = match formats::sniff(&input) {
+        Ok(format) => format,
+        Err(error) => {
+            job.errors.push(error.to_string());
+            return job;
+        }
+    };
+
+    if let Decoder::External(tool) = format.decoder {
+        if which::which(tool).is_err() {
+            job.errors.push(format!("Reading {} images needs '{}' installed.", format.name, tool));
+        }
+    }
+
+    // the crop applies to the image rotated upright, the same as when it's upscaled.
+    if let Some(crop) = &options.crop {
+        match orientation::upright_size(&input) {
+            Ok(size) => if let Err(error) = crop.validate(&size) {
+                job.errors.push(error.to_string());
+            },
+            Err(_) => job.warnings.push("Couldn't read the image size to check the crop against it.".to_string())
+        }
+    }
+
+    let animated = matches!(format.name, "GIF" | "WebP") && animation::is_animated(&input);
+
+    if animated && options.tiling.enabled {
+        job.warnings.push("Animations are upscaled frame by frame, tiling is ignored.".to_string());
+    }
+
+    if job.model.is_some() {
+        job.output = Some(upscale::output_path(&input, animated, options));
+    }
+
+    job
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+
+use image::{imageops::{self, FilterType}, DynamicImage, ImageDecoder, ImageReader, Rgb, RgbImage, RgbaImage};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+use crate::error::Error;
+
+use super::temp_path;
+
+/// How the alpha channel of transparent images gets upscaled. ESRGAN
+/// style models tend to fringe the edges when they upscale alpha themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Hash, EnumIter, Display, Serialize, Deserialize)]
+pub enum AlphaStrategy {
+    #[strum(to_string = "Let the model handle it")]
+    Model,
+    #[strum(to_string = "Resample alpha")]
+    Resample,
+    #[strum(to_string = "Upscale alpha separately")]
+    SeparateModel
+}
+
+/// The colour and alpha of an image written out as separate opaque PNGs.
+pub struct SplitImage {
+    pub colour: PathBuf,
+    pub alpha: PathBuf
+}
+
+/// Checks the colour type of the image without decoding the whole thing.
+pub fn has_alpha(path: &Path) -> bool {
+    ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .ok()
+        .and_then(|reader| reader.into_decoder().ok())
+        .is_some_and(|decoder| decoder.color_type().has_alpha())
+}
+
+pub fn split(path: &Path) -> Result<SplitImage, Error> {
+    let image = open(path)?.to_rgba8();
+
+    let mut colour = RgbImage::new(image.width(), image.height());
+    let mut alpha = RgbImage::new(image.width(), image.height());
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+
+        colour.put_pixel(x, y, Rgb([r, g, b]));
+        // the models expect colour images so the alpha is stored as grey.
+        alpha.put_pixel(x, y, Rgb([a, a, a]));
+    }
+
+    let split = SplitImage {
+        colour: temp_path(path, "colour"),
+        alpha: temp_path(path, "alpha")
+    };
+
+    save(&DynamicImage::ImageRgb8(colour), &split.colour)?;
+    save(&DynamicImage::ImageRgb8(alpha), &split.alpha)?;
+
+    Ok(split)
+}
+
+/// Puts the alpha back onto the upscaled colour image (in place), resampling 
+/// the alpha with Lanczos if it wasn't upscaled by the model.
+pub fn recombine(colour_path: &Path, alpha_path: &Path) -> Result<(), Error> {
+    let colour = open(colour_path)?.to_rgb8();
+    let mut alpha = open(alpha_path)?.to_luma8();
+
+    if alpha.dimensions() != colour.dimensions() {
+        alpha = imageops::resize(&alpha, colour.width(), colour.height(), FilterType::Lanczos3);
+    }
+
+    let mut combined = RgbaImage::new(colour.width(), colour.height());
+
+    for (x, y, pixel) in combined.enumerate_pixels_mut() {
+        let [r, g, b] = colour.get_pixel(x, y).0;
+        let [a] = alpha.get_pixel(x, y).0;
+
+        pixel.0 = [r, g, b, a];
+    }
+
+    save(&DynamicImage::ImageRgba8(combined), colour_path)
+}
+
+/// Blends a transparent image onto a solid background, for formats without alpha (JPG).
+pub fn flatten(image: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let image = image.to_rgba8();
+    let mut flattened = RgbImage::new(image.width(), image.height());
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        let [r, g, b, a] = pixel.0;
+        let alpha = a as f32 / 255.0;
+
+        let blend = |foreground: u8, background: u8| {
+            (foreground as f32 * alpha + background as f32 * (1.0 - alpha)).round() as u8
+        };
+
+        flattened.put_pixel(
+            x, y, Rgb([blend(r, background[0]), blend(g, background[1]), blend(b, background[2])])
+        );
+    }
+
+    DynamicImage::ImageRgb8(flattened)
+}
+
+fn open(path: &Path) -> Result<DynamicImage, Error> {
+    image::open(path).map_err(
+        |error| Error::FailedToProcessAlpha(Some(error.to_string()), "Failed to read image.".to_string())
+    )
+}
+
+fn save(image: &DynamicImage, path: &Path) -> Result<(), Error> {
+    image.save(path).map_err(
+        |error| Error::FailedToProcessAlpha(Some(error.to_string()), "Failed to save image.".to_string())
+    )
+}
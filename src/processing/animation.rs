@@ -0,0 +1,192 @@
+use std::{fs::{self, File}, io::BufReader, path::{Path, PathBuf}, process::Command};
+
+use image::{codecs::{gif::{GifDecoder, GifEncoder, Repeat}, webp::WebPDecoder}, AnimationDecoder, Delay, Frame, Frames};
+
+use crate::{error::Error, formats, upscale::OutputExt};
+
+use super::{encode::QualityOptions, temp_path};
+
+/// The frames of an animated GIF or WebP written out as PNGs so each one
+/// can be handed to upscayl-bin, along with the timing needed to put them back together.
+pub struct Animation {
+    pub frames: Vec<PathBuf>,
+    /// Delay of each frame in milliseconds.
+    pub delays: Vec<u32>,
+    /// How many times the animation plays, 0 meaning forever.
+    pub loop_count: u16
+}
+
+/// Whether the image at `path` is a GIF or WebP with more than one frame.
+pub fn is_animated(path: &Path) -> bool {
+    match decode_frames(path) {
+        Ok(frames) => frames.take(2).count() > 1,
+        Err(_) => false
+    }
+}
+
+/// The animated container we write, only WebP and GIF can hold animations.
+pub fn output_ext(output_ext: &OutputExt) -> OutputExt {
+    match output_ext {
+        OutputExt::WebP => OutputExt::WebP,
+        _ => OutputExt::GIF
+    }
+}
+
+pub fn extract(path: &Path) -> Result<Animation, Error> {
+    let frames = decode_frames(path)?;
+
+    let mut animation = Animation {
+        frames: Vec::new(),
+        delays: Vec::new(),
+        loop_count: read_loop_count(path)
+    };
+
+    for (index, frame) in frames.enumerate() {
+        let frame = frame.map_err(
+            |error| Error::FailedToProcessAnimation(Some(error.to_string()), "Failed to decode a frame.".to_string())
+        )?;
+
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        let frame_path = temp_path(path, &format!("frame_{}", index));
+
+        frame.buffer().save(&frame_path).map_err(
+            |error| Error::FailedToProcessAnimation(Some(error.to_string()), "Failed to save a frame.".to_string())
+        )?;
+
+        animation.frames.push(frame_path);
+        animation.delays.push(numerator / denominator.max(1));
+    }
+
+    Ok(animation)
+}
+
+/// Encodes the upscaled `frames` back into an animation with the original timing.
+pub fn assemble(frames: &[PathBuf], animation: &Animation, out: &Path, output_ext: &OutputExt, quality: &QualityOptions) -> Result<(), Error> {
+    match output_ext {
+        OutputExt::WebP => assemble_webp(frames, animation, out, quality),
+        _ => assemble_gif(frames, animation, out)
+    }
+}
+
+fn assemble_gif(frames: &[PathBuf], animation: &Animation, out: &Path) -> Result<(), Error> {
+    let encode_error = |error: String| Error::FailedToProcessAnimation(
+        Some(error), "Failed to encode the GIF.".to_string()
+    );
+
+    let file = File::create(out).map_err(|error| encode_error(error.to_string()))?;
+    let mut encoder = GifEncoder::new_with_speed(file, 10);
+
+    // GIFs count the repeats after the first play and play once without a loop extension.
+    let repeat = match animation.loop_count {
+        0 => Some(Repeat::Infinite),
+        1 => None,
+        count => Some(Repeat::Finite(count - 1))
+    };
+
+    if let Some(repeat) = repeat {
+        encoder.set_repeat(repeat).map_err(|error| encode_error(error.to_string()))?;
+    }
+
+    for (frame_path, delay) in frames.iter().zip(animation.delays.iter()) {
+        let buffer = image::open(frame_path)
+            .map_err(|error| encode_error(error.to_string()))?
+            .to_rgba8();
+
+        encoder.encode_frame(
+            Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(*delay, 1))
+        ).map_err(|error| encode_error(error.to_string()))?;
+    }
+
+    Ok(())
+}
+
+/// The image crate can't write animated WebPs so we use libwebp's img2webp.
+fn assemble_webp(frames: &[PathBuf], animation: &Animation, out: &Path, quality: &QualityOptions) -> Result<(), Error> {
+    let tool_path = which::which("img2webp").map_err(
+        |error| Error::FailedToProcessAnimation(
+            Some(error.to_string()), "Install 'img2webp' (libwebp) to save animated WebPs.".to_string()
+        )
+    )?;
+
+    let mut command = Command::new(tool_path);
+
+    #[cfg(target_os = "windows")] {
+        use std::os::windows::process::CommandExt;
+
+        command.creation_flags(0x08000000);
+    }
+
+    command.args(["-loop", &animation.loop_count.to_string()]);
+
+    match quality.webp_lossless {
+        true => command.arg("-lossless"),
+        false => command.args(["-lossy", "-q", &quality.webp_quality.to_string()])
+    };
+
+    for (frame_path, delay) in frames.iter().zip(animation.delays.iter()) {
+        command.args(["-d", &delay.to_string()]).arg(frame_path);
+    }
+
+    match command.arg("-o").arg(out).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(
+            Error::FailedToProcessAnimation(
+                Some(String::from_utf8_lossy(&output.stderr).to_string()),
+                "'img2webp' failed to encode the WebP.".to_string()
+            )
+        ),
+        Err(error) => Err(
+            Error::FailedToProcessAnimation(Some(error.to_string()), "Failed to run 'img2webp'.".to_string())
+        )
+    }
+}
+
+fn decode_frames(path: &Path) -> Result<Frames<'static>, Error> {
+    let decode_error = |error: String| Error::FailedToProcessAnimation(
+        Some(error), "Failed to read the animation.".to_string()
+    );
+
+    let reader = BufReader::new(
+        File::open(path).map_err(|error| decode_error(error.to_string()))?
+    );
+
+    let frames = match formats::sniff(path)?.name {
+        "WebP" => WebPDecoder::new(reader).map(|decoder| decoder.into_frames()),
+        "GIF" => GifDecoder::new(reader).map(|decoder| decoder.into_frames()),
+        name => return Err(decode_error(format!("{} images can't be animated.", name)))
+    };
+
+    frames.map_err(|error| decode_error(error.to_string()))
+}
+
+/// Reads the loop count from the GIF "NETSCAPE2.0" extension or the WebP "ANIM" chunk.
+fn read_loop_count(path: &Path) -> u16 {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return 0
+    };
+
+    let find = |needle: &[u8]| bytes.windows(needle.len()).position(|window| window == needle);
+
+    if let Some(position) = find(b"NETSCAPE2.0") {
+        // sub-block size (3), sub-block id (1) then the little endian loop count.
+        if let Some(&[3, 1, low, high]) = bytes.get(position + 11..position + 15) {
+            return match u16::from_le_bytes([low, high]) {
+                0 => 0,
+                repeats => repeats.saturating_add(1)
+            };
+        }
+    } else if bytes.starts_with(b"GIF") {
+        // GIFs without the extension only play once.
+        return 1;
+    }
+
+    if let Some(position) = find(b"ANIM") {
+        // chunk size (4) and background colour (4) come before the loop count.
+        if let Some(&[low, high]) = bytes.get(position + 12..position + 14) {
+            return u16::from_le_bytes([low, high]);
+        }
+    }
+
+    0
+}
@@ -0,0 +1,88 @@
+use std::{path::{Path, PathBuf}, process::Command};
+
+use image::{metadata::Orientation, ImageReader};
+
+use crate::{error::Error, formats::{Decoder, InputFormat}};
+
+use super::temp_path;
+
+/// Writes an upright PNG copy of the image to the temp folder so the preview 
+/// and upscayl-bin (which only read PNG, JPEG and WebP and ignore EXIF) can use it.
+pub fn to_png(path: &Path, format: &InputFormat, orientation: Orientation) -> Result<PathBuf, Error> {
+    let converted_path = temp_path(path, "converted");
+
+    if let Decoder::External(tool) = format.decoder {
+        // the external tools already apply the orientation themselves.
+        return convert_externally(tool, path, &converted_path);
+    }
+
+    let decode_error = |error: String| Error::FailedToInitImage(
+        Some(error),
+        path.to_path_buf(),
+        format!("Failed to decode the {} image.", format.name)
+    );
+
+    let mut reader = ImageReader::open(path)
+        .map_err(|error| decode_error(error.to_string()))?;
+
+    match format.decoder {
+        Decoder::Image(image_format) => reader.set_format(image_format),
+        _ => reader = reader.with_guessed_format()
+            .map_err(|error| decode_error(error.to_string()))?
+    }
+
+    let mut image = reader.decode()
+        .map_err(|error| decode_error(error.to_string()))?;
+
+    image.apply_orientation(orientation);
+
+    match image.save(&converted_path) {
+        Ok(_) => Ok(converted_path),
+        Err(error) => Err(
+            Error::FailedToInitImage(
+                Some(error.to_string()),
+                path.to_path_buf(),
+                "Failed to save the converted copy of the image.".to_string()
+            )
+        )
+    }
+}
+
+fn convert_externally(tool: &str, path: &Path, converted_path: &Path) -> Result<PathBuf, Error> {
+    let tool_path = match which::which(tool) {
+        Ok(tool_path) => tool_path,
+        Err(error) => return Err(
+            Error::FailedToInitImage(
+                Some(error.to_string()),
+                path.to_path_buf(),
+                format!("Install '{}' to open this image format.", tool)
+            )
+        )
+    };
+
+    let mut command = Command::new(tool_path);
+
+    #[cfg(target_os = "windows")] {
+        use std::os::windows::process::CommandExt;
+
+        command.creation_flags(0x08000000);
+    }
+
+    match command.arg(path).arg(converted_path).output() {
+        Ok(output) if output.status.success() => Ok(converted_path.to_path_buf()),
+        Ok(output) => Err(
+            Error::FailedToInitImage(
+                Some(String::from_utf8_lossy(&output.stderr).to_string()),
+                path.to_path_buf(),
+                format!("'{}' failed to convert the image.", tool)
+            )
+        ),
+        Err(error) => Err(
+            Error::FailedToInitImage(
+                Some(error.to_string()),
+                path.to_path_buf(),
+                format!("Failed to run '{}'.", tool)
+            )
+        )
+    }
+}
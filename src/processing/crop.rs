@@ -0,0 +1,91 @@
+use std::{fmt::{self, Display, Formatter}, path::{Path, PathBuf}, str::FromStr};
+
+use imagesize::ImageSize;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+use super::temp_path;
+
+/// A region of the input image (in pixels) that should be 
+/// upscaled instead of the whole image.
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Serialize, Deserialize)]
+pub struct Crop {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl Crop {
+    /// Makes sure the crop region is not empty and lies within the image.
+    pub fn validate(&self, image_size: &ImageSize) -> Result<(), Error> {
+        let fits = self.x as usize + self.width as usize <= image_size.width 
+            && self.y as usize + self.height as usize <= image_size.height;
+
+        if self.width == 0 || self.height == 0 || !fits {
+            return Err(
+                Error::InvalidCrop(
+                    None,
+                    *self,
+                    format!(
+                        "It must be inside the image ({}x{}).", image_size.width, image_size.height
+                    )
+                )
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Crops the image at `image_path` and writes the region 
+    /// to a temporary PNG that can be handed to the backend.
+    pub fn apply(&self, image_path: &Path) -> Result<PathBuf, Error> {
+        let image = match image::open(image_path) {
+            Ok(image) => image,
+            Err(error) => return Err(
+                Error::FailedToUpscaleImage(
+                    Some(error.to_string()),
+                    "Failed to read the image for cropping.".to_string()
+                )
+            )
+        };
+
+        let cropped_path = temp_path(image_path, "crop");
+
+        let cropped = image.crop_imm(self.x, self.y, self.width, self.height);
+
+        match cropped.save(&cropped_path) {
+            Ok(_) => Ok(cropped_path),
+            Err(error) => Err(
+                Error::FailedToUpscaleImage(
+                    Some(error.to_string()),
+                    "Failed to save the cropped image.".to_string()
+                )
+            )
+        }
+    }
+}
+
+impl FromStr for Crop {
+    type Err = String;
+
+    /// Parses a crop in the `x,y,w,h` format.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let values = string.split(',')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(|error| format!("'{}' is not a valid crop: {}", string, error))?;
+
+        match values[..] {
+            [x, y, width, height] => Ok(Self { x, y, width, height }),
+            _ => Err(format!("'{}' is not a valid crop, expected 'x,y,w,h'.", string))
+        }
+    }
+}
+
+impl Display for Crop {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
@@ -0,0 +1,164 @@
+use std::{fs::File, io::BufWriter, path::Path, process::Command};
+
+use image::{codecs::{avif::AvifEncoder, gif::GifEncoder, jpeg::JpegEncoder, png::{CompressionType, FilterType, PngEncoder}, tiff::TiffEncoder, webp::WebPEncoder}, Frame};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter};
+
+use crate::{error::Error, upscale::OutputExt};
+
+use super::alpha;
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Display, Serialize, Deserialize)]
+pub enum PngCompression {
+    #[strum(to_string = "Fast")]
+    Fast,
+    #[strum(to_string = "Default")]
+    Default,
+    #[strum(to_string = "Best")]
+    Best
+}
+
+/// Encoder settings for each output format, only the
+/// ones for the selected output format are used.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityOptions {
+    pub jpg_quality: u8,
+    pub webp_lossless: bool,
+    pub webp_quality: u8,
+    pub png_compression: PngCompression,
+    pub avif_quality: u8,
+    pub avif_speed: u8,
+    /// Colour transparent images are flattened onto when saving as JPG.
+    pub jpg_background: [u8; 3]
+}
+
+impl Default for QualityOptions {
+    fn default() -> Self {
+        Self {
+            jpg_quality: 100,
+            webp_lossless: false,
+            webp_quality: 100,
+            png_compression: PngCompression::Default,
+            avif_quality: 80,
+            avif_speed: 6,
+            jpg_background: [255, 255, 255]
+        }
+    }
+}
+
+impl QualityOptions {
+    /// Whether upscayl-bin writes the output format itself, otherwise
+    /// it writes a PNG that we encode to the output format afterwards.
+    pub fn backend_writes(&self, output_ext: &OutputExt) -> bool {
+        match output_ext {
+            OutputExt::JPG => true,
+            OutputExt::WebP => !self.webp_lossless,
+            _ => false
+        }
+    }
+
+    /// The `-c` value for upscayl-bin. It takes a compression
+    /// amount (0 being the best quality) rather than a quality.
+    pub fn backend_compression(&self, output_ext: &OutputExt) -> u8 {
+        match output_ext {
+            OutputExt::JPG => 100 - self.jpg_quality,
+            OutputExt::WebP => 100 - self.webp_quality,
+            _ => 0
+        }
+    }
+}
+
+/// Encodes the PNG upscayl-bin wrote at `upscaled_path` to the output format at `out`.
+pub fn encode(upscaled_path: &Path, out: &Path, output_ext: &OutputExt, quality: &QualityOptions) -> Result<(), Error> {
+    if *output_ext == OutputExt::WebP && !quality.webp_lossless {
+        return encode_lossy_webp(upscaled_path, out, quality);
+    }
+
+    let image = match image::open(upscaled_path) {
+        Ok(image) => image,
+        Err(error) => return Err(
+            Error::FailedToEncodeImage(
+                Some(error.to_string()), output_ext.to_string(), "Failed to read the upscaled image.".to_string()
+            )
+        )
+    };
+
+    let writer = match File::create(out) {
+        Ok(file) => BufWriter::new(file),
+        Err(error) => return Err(
+            Error::FailedToEncodeImage(
+                Some(error.to_string()), output_ext.to_string(), "Failed to create the output file.".to_string()
+            )
+        )
+    };
+
+    let result = match output_ext {
+        OutputExt::PNG => {
+            let compression = match quality.png_compression {
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Default => CompressionType::Default,
+                PngCompression::Best => CompressionType::Best,
+            };
+
+            image.write_with_encoder(
+                PngEncoder::new_with_quality(writer, compression, FilterType::Adaptive)
+            )
+        },
+        // lossy WebPs are handed to cwebp above.
+        OutputExt::WebP => image.write_with_encoder(WebPEncoder::new_lossless(writer)),
+        OutputExt::AVIF => image.write_with_encoder(
+            AvifEncoder::new_with_speed_quality(writer, quality.avif_speed, quality.avif_quality)
+        ),
+        OutputExt::TIFF => image.write_with_encoder(TiffEncoder::new(writer)),
+        OutputExt::GIF => GifEncoder::new(writer).encode_frame(Frame::new(image.to_rgba8())),
+        // JPEG has no alpha channel.
+        OutputExt::JPG => alpha::flatten(&image, quality.jpg_background).write_with_encoder(
+            JpegEncoder::new_with_quality(writer, quality.jpg_quality)
+        )
+    };
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(error) => Err(
+            Error::FailedToEncodeImage(
+                Some(error.to_string()), output_ext.to_string(), "The encoder failed.".to_string()
+            )
+        )
+    }
+}
+
+
+/// The image crate can only write lossless WebPs so we use libwebp's cwebp for lossy ones.
+fn encode_lossy_webp(upscaled_path: &Path, out: &Path, quality: &QualityOptions) -> Result<(), Error> {
+    let tool_path = which::which("cwebp").map_err(
+        |error| Error::FailedToEncodeImage(
+            Some(error.to_string()),
+            OutputExt::WebP.to_string(),
+            "Install 'cwebp' (libwebp) to save lossy WebPs or tick \"Lossless\".".to_string()
+        )
+    )?;
+
+    let mut command = Command::new(tool_path);
+
+    #[cfg(target_os = "windows")] {
+        use std::os::windows::process::CommandExt;
+
+        command.creation_flags(0x08000000);
+    }
+
+    command.args(["-quiet", "-q", &quality.webp_quality.to_string()]);
+
+    match command.arg(upscaled_path).arg("-o").arg(out).output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(
+            Error::FailedToEncodeImage(
+                Some(String::from_utf8_lossy(&output.stderr).to_string()),
+                OutputExt::WebP.to_string(),
+                "'cwebp' failed to encode the WebP.".to_string()
+            )
+        ),
+        Err(error) => Err(
+            Error::FailedToEncodeImage(Some(error.to_string()), OutputExt::WebP.to_string(), "Failed to run 'cwebp'.".to_string())
+        )
+    }
+}
\ No newline at end of file
@@ -0,0 +1,212 @@
+use std::{fs, io::Cursor, path::Path};
+
+use exif::{experimental::Writer, Field, In, Reader, Tag, Value};
+use img_parts::{jpeg::{markers, JpegSegment}, png::PngChunk, Bytes, DynImage, ImageEXIF, ImageICC};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use crate::error::Error;
+
+const JPEG_XMP_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PNG_XMP_KEYWORD: &[u8] = b"XML:com.adobe.xmp\0";
+
+/// What metadata from the source image should end up in the upscaled image.
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Display, EnumString, Serialize, Deserialize)]
+#[strum(ascii_case_insensitive)]
+pub enum MetadataPolicy {
+    #[strum(to_string = "Keep all")]
+    KeepAll,
+    #[strum(to_string = "Keep colour only")]
+    KeepColourOnly,
+    #[strum(to_string = "Strip all")]
+    StripAll
+}
+
+/// Metadata read from the source image before handing it to upscayl-bin (which drops it).
+#[derive(Default)]
+pub struct Metadata {
+    icc_profile: Option<Bytes>,
+    exif: Option<Bytes>,
+    xmp: Option<Bytes>
+}
+
+impl Metadata {
+    /// Reads the ICC profile, EXIF and XMP of a PNG, JPEG or WebP. Other
+    /// formats just return empty metadata as there is nothing we can copy.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let image = match read_container(path)? {
+            Some(image) => image,
+            None => return Ok(Self::default())
+        };
+
+        let xmp = match &image {
+            DynImage::Jpeg(jpeg) => jpeg.segments_by_marker(markers::APP1)
+                .find(|segment| segment.contents().starts_with(JPEG_XMP_PREFIX))
+                .map(|segment| segment.contents().slice(JPEG_XMP_PREFIX.len()..)),
+            DynImage::Png(png) => png.chunks_by_type(*b"iTXt")
+                .find(|chunk| chunk.contents().starts_with(PNG_XMP_KEYWORD))
+                // keyword, compression flag + method, empty language tag and translated keyword.
+                .and_then(|chunk| chunk.contents().get(PNG_XMP_KEYWORD.len() + 4..).map(Bytes::copy_from_slice)),
+            DynImage::WebP(_) => None
+        };
+
+        Ok(Self {
+            icc_profile: image.icc_profile(),
+            exif: image.exif(),
+            xmp
+        })
+    }
+
+    /// Writes this metadata into the image at `path` following `policy`, updating the EXIF
+    /// dimension tags to the upscaled `dimensions`. Only PNG, JPEG and WebP (without XMP)
+    /// can hold it, any other output with metadata to keep errors so it doesn't go missing unnoticed.
+    pub fn write(&self, path: &Path, policy: MetadataPolicy, dimensions: (u32, u32)) -> Result<(), Error> {
+        let (icc_profile, exif, xmp) = match policy {
+            MetadataPolicy::KeepAll => (
+                self.icc_profile.clone(),
+                self.rewrite_exif(dimensions, false),
+                self.xmp.clone()
+            ),
+            MetadataPolicy::KeepColourOnly => (
+                self.icc_profile.clone(),
+                self.rewrite_exif(dimensions, true),
+                None
+            ),
+            MetadataPolicy::StripAll => (None, None, None)
+        };
+
+        let mut image = match read_container(path)? {
+            Some(image) => image,
+            None if icc_profile.is_none() && exif.is_none() && xmp.is_none() => return Ok(()),
+            None => return Err(
+                Error::FailedToCopyMetadata(
+                    None,
+                    format!(
+                        "{} outputs can't hold metadata, it was left out. Save as PNG, JPG or WebP to keep it.",
+                        path.extension().unwrap_or_default().to_string_lossy().to_uppercase()
+                    )
+                )
+            )
+        };
+
+        image.set_icc_profile(icc_profile);
+        image.set_exif(exif);
+
+        match &mut image {
+            DynImage::Jpeg(jpeg) => {
+                let segments = jpeg.segments_mut();
+
+                segments.retain(
+                    |segment| !(segment.marker() == markers::APP1 && segment.contents().starts_with(JPEG_XMP_PREFIX))
+                );
+
+                if let Some(xmp) = xmp {
+                    let position = segments.iter()
+                        .position(|segment| !(markers::APP0..=markers::APP15).contains(&segment.marker()))
+                        .unwrap_or(0);
+
+                    segments.insert(
+                        position,
+                        JpegSegment::new_with_contents(markers::APP1, [JPEG_XMP_PREFIX, &xmp].concat().into())
+                    );
+                }
+            },
+            DynImage::Png(png) => {
+                let chunks = png.chunks_mut();
+
+                chunks.retain(
+                    |chunk| !(chunk.kind() == *b"iTXt" && chunk.contents().starts_with(PNG_XMP_KEYWORD))
+                );
+
+                if let Some(xmp) = xmp {
+                    let position = chunks.iter()
+                        .position(|chunk| chunk.kind() == *b"IDAT")
+                        .unwrap_or(chunks.len());
+
+                    chunks.insert(
+                        position,
+                        PngChunk::new(*b"iTXt", [PNG_XMP_KEYWORD, &[0, 0, 0, 0], &xmp].concat().into())
+                    );
+                }
+            },
+            // img-parts can't set the VP8X flag for XMP so we leave it out for WebP.
+            DynImage::WebP(_) => {}
+        }
+
+        let file = match fs::File::create(path) {
+            Ok(file) => file,
+            Err(error) => return Err(
+                Error::FailedToCopyMetadata(Some(error.to_string()), "Failed to open the output image.".to_string())
+            )
+        };
+
+        match image.encoder().write_to(file) {
+            Ok(_) => Ok(()),
+            Err(error) => Err(
+                Error::FailedToCopyMetadata(Some(error.to_string()), "Failed to write the output image.".to_string())
+            )
+        }
+    }
+
+    /// Re-encodes the source EXIF with the thumbnail dropped and the dimension and
+    /// orientation tags updated. When `colour_only` is set only colour related fields are kept.
+    fn rewrite_exif(&self, dimensions: (u32, u32), colour_only: bool) -> Option<Bytes> {
+        let exif = Reader::new().read_raw(self.exif.as_ref()?.to_vec()).ok()?;
+
+        let (width, height) = dimensions;
+
+        let fields: Vec<Field> = exif.fields()
+            .filter(|field| field.ifd_num == In::PRIMARY)
+            .filter(|field| !colour_only || matches!(field.tag, Tag::ColorSpace | Tag::Gamma | Tag::WhitePoint | Tag::PrimaryChromaticities))
+            .map(|field| match field.tag {
+                Tag::PixelXDimension | Tag::ImageWidth => Field {
+                    value: Value::Long(vec![width]), ..field.clone()
+                },
+                Tag::PixelYDimension | Tag::ImageLength => Field {
+                    value: Value::Long(vec![height]), ..field.clone()
+                },
+                // the pixels we upscaled were already rotated upright.
+                Tag::Orientation => Field {
+                    value: Value::Short(vec![1]), ..field.clone()
+                },
+                _ => field.clone()
+            })
+            .collect();
+
+        if fields.is_empty() {
+            return None;
+        }
+
+        let mut writer = Writer::new();
+
+        for field in fields.iter() {
+            writer.push_field(field);
+        }
+
+        let mut buffer = Cursor::new(Vec::new());
+
+        match writer.write(&mut buffer, exif.little_endian()) {
+            Ok(_) => Some(buffer.into_inner().into()),
+            Err(error) => {
+                log::warn!("Failed to rewrite EXIF metadata, dropping it! Error: {}", error);
+                None
+            }
+        }
+    }
+}
+
+fn read_container(path: &Path) -> Result<Option<DynImage>, Error> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(error) => return Err(
+            Error::FailedToCopyMetadata(Some(error.to_string()), "Failed to read image.".to_string())
+        )
+    };
+
+    match DynImage::from_bytes(bytes.into()) {
+        Ok(image) => Ok(image),
+        Err(error) => Err(
+            Error::FailedToCopyMetadata(Some(error.to_string()), "Failed to parse image.".to_string())
+        )
+    }
+}
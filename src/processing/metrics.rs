@@ -0,0 +1,113 @@
+use image::{GrayImage, RgbImage};
+
+/// SSIM is computed over windows of this size, moved along by half a window at a time.
+const SSIM_WINDOW: u32 = 8;
+
+/// Peak signal-to-noise ratio in decibels between two images of the same size,
+/// higher is closer. Identical images give infinity.
+pub fn psnr(image: &RgbImage, reference: &RgbImage) -> f64 {
+    let squared_error: f64 = image.as_raw().iter()
+        .zip(reference.as_raw().iter())
+        .map(|(a, b)| (*a as f64 - *b as f64).powi(2))
+        .sum();
+
+    let mse = squared_error / image.as_raw().len().max(1) as f64;
+
+    if mse == 0.0 {
+        return f64::INFINITY;
+    }
+
+    10.0 * (255.0_f64.powi(2) / mse).log10()
+}
+
+/// Mean structural similarity of the luma of two images of the same size, 1 being identical.
+pub fn ssim(image: &GrayImage, reference: &GrayImage) -> f64 {
+    let c1 = (0.01_f64 * 255.0).powi(2);
+    let c2 = (0.03_f64 * 255.0).powi(2);
+
+    let (width, height) = image.dimensions();
+    let window = SSIM_WINDOW.min(width).min(height);
+
+    if window == 0 {
+        return 0.0;
+    }
+
+    let stride = (window / 2).max(1);
+
+    let mut total = 0.0;
+    let mut windows = 0;
+
+    let mut y = 0;
+
+    while y + window <= height {
+        let mut x = 0;
+
+        while x + window <= width {
+            let pixels = (window * window) as f64;
+
+            let (mut sum_a, mut sum_b) = (0.0, 0.0);
+
+            for (a, b) in window_pixels(image, reference, x, y, window) {
+                sum_a += a;
+                sum_b += b;
+            }
+
+            let (mean_a, mean_b) = (sum_a / pixels, sum_b / pixels);
+            let (mut variance_a, mut variance_b, mut covariance) = (0.0, 0.0, 0.0);
+
+            for (a, b) in window_pixels(image, reference, x, y, window) {
+                variance_a += (a - mean_a).powi(2);
+                variance_b += (b - mean_b).powi(2);
+                covariance += (a - mean_a) * (b - mean_b);
+            }
+
+            let (variance_a, variance_b, covariance) = (
+                variance_a / pixels, variance_b / pixels, covariance / pixels
+            );
+
+            total += ((2.0 * mean_a * mean_b + c1) * (2.0 * covariance + c2))
+                / ((mean_a.powi(2) + mean_b.powi(2) + c1) * (variance_a + variance_b + c2));
+            windows += 1;
+
+            x += stride;
+        }
+
+        y += stride;
+    }
+
+    total / windows.max(1) as f64
+}
+
+/// Variance of the laplacian of the luma, higher means more fine detail (or more noise).
+pub fn sharpness(image: &GrayImage) -> f64 {
+    let (width, height) = image.dimensions();
+
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let pixel = |x: u32, y: u32| image.get_pixel(x, y).0[0] as f64;
+
+    let mut responses = Vec::with_capacity(((width - 2) * (height - 2)) as usize);
+
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            responses.push(
+                pixel(x - 1, y) + pixel(x + 1, y) + pixel(x, y - 1) + pixel(x, y + 1) - 4.0 * pixel(x, y)
+            );
+        }
+    }
+
+    let mean = responses.iter().sum::<f64>() / responses.len() as f64;
+
+    responses.iter().map(|response| (response - mean).powi(2)).sum::<f64>() / responses.len() as f64
+}
+
+fn window_pixels<'a>(image: &'a GrayImage, reference: &'a GrayImage, x: u32, y: u32, window: u32) -> impl Iterator<Item = (f64, f64)> + 'a {
+    (y..y + window).flat_map(move |pixel_y| {
+        (x..x + window).map(move |pixel_x| (
+            image.get_pixel(pixel_x, pixel_y).0[0] as f64,
+            reference.get_pixel(pixel_x, pixel_y).0[0] as f64
+        ))
+    })
+}
@@ -0,0 +1,46 @@
+use std::{env, fs, path::{Path, PathBuf}, process, sync::atomic::{AtomicU64, Ordering}};
+
+pub mod alpha;
+pub mod animation;
+pub mod convert;
+pub mod crop;
+pub mod encode;
+pub mod metadata;
+pub mod metrics;
+pub mod orientation;
+pub mod tiling;
+
+/// A temporary file that's deleted once the last clone of it is dropped.
+#[derive(Debug)]
+pub struct TempFile(pub PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Counts up with every temp path so no two are the same, even for images
+/// with the same name (or the same image) in jobs running at the same time.
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Returns a new path in aeternum's temp folder for an intermediate
+/// file derived from `image_path` (e.g. a cropped copy of the input).
+pub fn temp_path(image_path: &Path, suffix: &str) -> PathBuf {
+    let temp_folder = env::temp_dir().join("aeternum");
+
+    if !temp_folder.exists() {
+        // if this fails writing the temp file will fail too and report it.
+        let _ = fs::create_dir_all(&temp_folder);
+    }
+
+    temp_folder.join(
+        format!(
+            "{}_{}_{}_{}.png",
+            image_path.file_stem().unwrap().to_string_lossy(),
+            suffix,
+            process::id(),
+            NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+        )
+    )
+}
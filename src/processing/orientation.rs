@@ -0,0 +1,39 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use exif::{In, Reader, Tag};
+use image::metadata::Orientation;
+use imagesize::{ImageResult, ImageSize};
+
+/// Reads the EXIF orientation of an image, if it has none (or 
+/// we fail to read it) we assume the image is already upright.
+pub fn read_orientation(path: &Path) -> Orientation {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Orientation::NoTransforms
+    };
+
+    let exif = match Reader::new().read_from_container(&mut BufReader::new(file)) {
+        Ok(exif) => exif,
+        Err(_) => return Orientation::NoTransforms
+    };
+
+    exif.get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| Orientation::from_exif(value as u8))
+        .unwrap_or(Orientation::NoTransforms)
+}
+
+/// The size of the image once it's rotated upright, read from its header without decoding it.
+pub fn upright_size(path: &Path) -> ImageResult<ImageSize> {
+    let size = imagesize::size(path)?;
+
+    Ok(
+        match read_orientation(path) {
+            Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Rotate90FlipH | Orientation::Rotate270FlipH => ImageSize {
+                width: size.height,
+                height: size.width
+            },
+            _ => size
+        }
+    )
+}
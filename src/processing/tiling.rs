@@ -0,0 +1,315 @@
+use std::{env, fs::{self, File}, hash::{DefaultHasher, Hash, Hasher}, io::{BufWriter, Write}, path::{Path, PathBuf}};
+
+use image::{imageops, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+use super::encode::PngCompression;
+
+#[derive(Debug, Clone, PartialEq, Hash, Serialize, Deserialize)]
+pub struct TilingOptions {
+    pub enabled: bool,
+    /// Width and height of each tile in source pixels.
+    pub tile_size: u32,
+    /// How many source pixels neighbouring tiles share, the seams get blended across this.
+    pub overlap: u32
+}
+
+impl Default for TilingOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tile_size: 1024,
+            overlap: 32
+        }
+    }
+}
+
+/// A region of the source image that gets upscaled on its own.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32
+}
+
+impl TilingOptions {
+    /// Splits an image of the given size into overlapping tiles, left to right, top to bottom.
+    pub fn plan(&self, width: u32, height: u32) -> Vec<Tile> {
+        let tile_size = self.tile_size.max(self.overlap * 2 + 1);
+
+        let starts = |length: u32| {
+            let stride = tile_size - self.overlap;
+            let mut starts = vec![0];
+
+            while starts.last().unwrap() + tile_size < length {
+                // the last tile is pushed back so it doesn't hang off the edge.
+                starts.push((starts.last().unwrap() + stride).min(length - tile_size));
+            }
+
+            starts
+        };
+
+        let mut tiles = Vec::new();
+
+        for y in starts(height) {
+            for x in starts(width) {
+                tiles.push(
+                    Tile {
+                        x,
+                        y,
+                        width: tile_size.min(width - x),
+                        height: tile_size.min(height - y)
+                    }
+                );
+            }
+        }
+
+        tiles
+    }
+}
+
+/// Where the tiles of a job live. The folder is derived from the job so if a tile
+/// fails, upscaling the same image with the same settings again resumes from it.
+pub fn tile_folder(job: impl Hash) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    job.hash(&mut hasher);
+
+    env::temp_dir()
+        .join("aeternum")
+        .join("tiles")
+        .join(format!("{:016x}", hasher.finish()))
+}
+
+/// Writes every tile of the image at `path` into `folder`, returning their paths.
+pub fn split(path: &Path, tiles: &[Tile], folder: &Path) -> Result<Vec<PathBuf>, Error> {
+    let image = image::open(path).map_err(
+        |error| Error::FailedToTileImage(Some(error.to_string()), "Failed to read the image.".to_string())
+    )?;
+
+    fs::create_dir_all(folder).map_err(
+        |error| Error::FailedToTileImage(Some(error.to_string()), "Failed to create the tile folder.".to_string())
+    )?;
+
+    let mut tile_paths = Vec::with_capacity(tiles.len());
+
+    for (index, tile) in tiles.iter().enumerate() {
+        let tile_path = folder.join(format!("tile_{}.png", index));
+
+        image.crop_imm(tile.x, tile.y, tile.width, tile.height)
+            .save(&tile_path)
+            .map_err(
+                |error| Error::FailedToTileImage(Some(error.to_string()), "Failed to save a tile.".to_string())
+            )?;
+
+        tile_paths.push(tile_path);
+    }
+
+    Ok(tile_paths)
+}
+
+/// Puts the upscaled tiles back together, feathering each tile into the
+/// ones before it across the overlap so the seams don't show. The PNG is
+/// written a row of tiles at a time so the whole image never sits in memory.
+pub fn stitch(
+    tiles: &[Tile],
+    upscaled_tiles: &[PathBuf],
+    options: &TilingOptions,
+    scale: u32,
+    compression: PngCompression,
+    out: &Path
+) -> Result<(), Error> {
+    let stitch_error = |error: String, reason: &str| Error::FailedToTileImage(Some(error), reason.to_string());
+
+    let width = tiles.iter().map(|tile| tile.x + tile.width).max().unwrap_or(0) * scale;
+    let height = tiles.iter().map(|tile| tile.y + tile.height).max().unwrap_or(0) * scale;
+    let feather = (options.overlap * scale).max(1) as f32;
+
+    let file = File::create(out)
+        .map_err(|error| stitch_error(error.to_string(), "Failed to create the stitched image."))?;
+
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(
+        match compression {
+            PngCompression::Fast => png::Compression::Fast,
+            PngCompression::Default => png::Compression::Balanced,
+            PngCompression::Best => png::Compression::High
+        }
+    );
+
+    let mut writer = encoder.write_header()
+        .and_then(|writer| writer.into_stream_writer())
+        .map_err(|error| stitch_error(error.to_string(), "Failed to write the stitched image."))?;
+
+    // the rows of the current row of tiles, starting at output row `band_top`.
+    let mut band = RgbaImage::new(width, 0);
+    let mut band_top = 0;
+
+    for (tile, tile_path) in tiles.iter().zip(upscaled_tiles) {
+        let (offset_x, offset_y) = (tile.x * scale, tile.y * scale);
+        let tile_bottom = offset_y + tile.height * scale;
+
+        // a new row of tiles, the rows above it won't be touched again so they can be written out.
+        if offset_y > band_top {
+            let done_rows = (offset_y - band_top).min(band.height());
+            let row_bytes = width as usize * 4;
+
+            writer.write_all(&band.as_raw()[..done_rows as usize * row_bytes])
+                .map_err(|error| stitch_error(error.to_string(), "Failed to write the stitched image."))?;
+
+            let mut next_band = RgbaImage::new(width, tile_bottom - offset_y);
+            let kept = &band.as_raw()[done_rows as usize * row_bytes..];
+
+            next_band.as_mut()[..kept.len()].copy_from_slice(kept);
+
+            band = next_band;
+            band_top = offset_y;
+        }
+
+        if tile_bottom - band_top > band.height() {
+            let mut taller_band = RgbaImage::new(width, tile_bottom - band_top);
+
+            taller_band.as_mut()[..band.as_raw().len()].copy_from_slice(band.as_raw());
+            band = taller_band;
+        }
+
+        let mut upscaled = image::open(tile_path).map_err(
+            |error| stitch_error(error.to_string(), "Failed to read an upscaled tile.")
+        )?.to_rgba8();
+
+        // the model should give us exactly `scale` times the tile, but just in case.
+        if upscaled.dimensions() != (tile.width * scale, tile.height * scale) {
+            upscaled = imageops::resize(
+                &upscaled, tile.width * scale, tile.height * scale, imageops::FilterType::Lanczos3
+            );
+        }
+
+        for (x, y, pixel) in upscaled.enumerate_pixels() {
+            // only the left and top edges overlap tiles that were already placed.
+            let weight_x = if tile.x > 0 { (x as f32 / feather).min(1.0) } else { 1.0 };
+            let weight_y = if tile.y > 0 { (y as f32 / feather).min(1.0) } else { 1.0 };
+            let weight = weight_x * weight_y;
+
+            let existing = band.get_pixel_mut(offset_x + x, offset_y - band_top + y);
+
+            for channel in 0..4 {
+                existing.0[channel] = (
+                    pixel.0[channel] as f32 * weight + existing.0[channel] as f32 * (1.0 - weight)
+                ).round() as u8;
+            }
+        }
+    }
+
+    writer.write_all(band.as_raw())
+        .and_then(|_| writer.finish().map_err(std::io::Error::other))
+        .map_err(|error| stitch_error(error.to_string(), "Failed to save the stitched image."))
+}
+
+/// Outputs bigger than this decoded (4 GB) get a warning before they're encoded.
+pub const LARGE_IMAGE_MEMORY: u64 = 4 * 1024 * 1024 * 1024;
+
+/// Roughly how much memory an RGBA image of this size takes up decoded.
+pub fn memory_estimate(width: u64, height: u64) -> u64 {
+    width * height * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use crate::test_utils::TestFolder;
+
+    use super::*;
+
+    #[test]
+    fn stitches_tiles_back_into_the_image() {
+        let folder = TestFolder::new("tiling-stitch");
+        let path = folder.join("image.png");
+        let out = folder.join("stitched.png");
+
+        let image = RgbaImage::from_fn(50, 37, |x, y| Rgba([(x * 5) as u8, (y * 6) as u8, (x + y) as u8, 255]));
+        image.save(&path).unwrap();
+
+        let options = TilingOptions { enabled: true, tile_size: 16, overlap: 4 };
+        let tiles = options.plan(50, 37);
+
+        let tile_paths = split(&path, &tiles, &folder).unwrap();
+
+        stitch(&tiles, &tile_paths, &options, 1, PngCompression::Fast, &out).unwrap();
+
+        assert_eq!(image::open(&out).unwrap().to_rgba8(), image);
+    }
+
+    #[test]
+    fn stitches_upscaled_tiles_at_their_scaled_offsets() {
+        let folder = TestFolder::new("tiling-stitch-scaled");
+        let path = folder.join("image.png");
+        let out = folder.join("stitched.png");
+
+        let image = RgbaImage::from_fn(50, 37, |x, y| Rgba([(x * 5) as u8, (y * 6) as u8, (x + y) as u8, 255]));
+        image.save(&path).unwrap();
+
+        let options = TilingOptions { enabled: true, tile_size: 16, overlap: 4 };
+        let tiles = options.plan(50, 37);
+
+        // nearest neighbour stands in for the model, each tile then matches its part of the whole image upscaled.
+        let upscaled_tiles: Vec<PathBuf> = split(&path, &tiles, &folder).unwrap()
+            .iter()
+            .enumerate()
+            .map(|(index, tile_path)| {
+                let tile = image::open(tile_path).unwrap().to_rgba8();
+                let upscaled_path = folder.join(format!("upscaled_{}.png", index));
+
+                imageops::resize(&tile, tile.width() * 3, tile.height() * 3, imageops::FilterType::Nearest)
+                    .save(&upscaled_path)
+                    .unwrap();
+
+                upscaled_path
+            })
+            .collect();
+
+        stitch(&tiles, &upscaled_tiles, &options, 3, PngCompression::Fast, &out).unwrap();
+
+        let expected = imageops::resize(&image, 150, 111, imageops::FilterType::Nearest);
+
+        assert_eq!(image::open(&out).unwrap().to_rgba8(), expected);
+    }
+
+    #[test]
+    fn feathers_tiles_into_each_other_across_the_overlap() {
+        let folder = TestFolder::new("tiling-feather");
+        let out = folder.join("stitched.png");
+
+        // two tiles side by side, the second starting at x 12 so they share 4 source pixels.
+        let options = TilingOptions { enabled: true, tile_size: 16, overlap: 4 };
+        let tiles = options.plan(28, 16);
+
+        assert_eq!(tiles.iter().map(|tile| tile.x).collect::<Vec<_>>(), [0, 12]);
+
+        let upscaled_tiles = [(0, 0), (1, 255)].map(|(index, value)| {
+            let upscaled_path = folder.join(format!("upscaled_{}.png", index));
+
+            RgbaImage::from_pixel(32, 32, Rgba([value, value, value, 255])).save(&upscaled_path).unwrap();
+
+            upscaled_path
+        });
+
+        stitch(&tiles, &upscaled_tiles, &options, 2, PngCompression::Fast, &out).unwrap();
+
+        let stitched = image::open(&out).unwrap().to_rgba8();
+
+        assert_eq!(stitched.dimensions(), (56, 32));
+
+        // the overlap is 8 pixels wide once upscaled, starting where the second tile does.
+        let row: Vec<u8> = (0..56).map(|x| stitched.get_pixel(x, 20).0[0]).collect();
+
+        assert!(row[..24].iter().all(|value| *value == 0));
+        assert_eq!(row[24..32], [0, 32, 64, 96, 128, 159, 191, 223]);
+        assert!(row[32..].iter().all(|value| *value == 255));
+    }
+}
@@ -0,0 +1,383 @@
+use std::{path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, Sender}, Arc, Mutex}, thread, time::Instant};
+
+use crate::{config::structs::workers::Workers, error::Error, upscale::{Backend, BackendKind, Progress, Stage}};
+
+/// Where a job is at. Jobs start `Idle` and end up `Succeeded`, `Skipped`, `Failed` or `Cancelled`.
+#[derive(Debug, Clone)]
+pub enum JobState {
+    /// Waiting for a free worker.
+    Idle,
+    /// Cropping, splitting or extracting, anything before upscayl-bin runs.
+    Preparing,
+    Running {
+        /// The last progress line upscayl-bin printed.
+        progress: String
+    },
+    /// Stitching, recombining, encoding or copying metadata.
+    PostProcessing,
+    Succeeded {
+        output: PathBuf
+    },
+    /// A resumed job whose output was already there.
+    Skipped {
+        output: PathBuf
+    },
+    Failed {
+        error: Error
+    },
+    Cancelled
+}
+
+impl JobState {
+    pub fn finished(&self) -> bool {
+        matches!(
+            self,
+            JobState::Succeeded { .. } | JobState::Skipped { .. } | JobState::Failed { .. } | JobState::Cancelled
+        )
+    }
+}
+
+/// What a job does, only upscales have an output the app shows once they're done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JobKind {
+    Upscale,
+    Compare,
+    Bench
+}
+
+/// A job moving to a new state.
+#[derive(Debug, Clone)]
+pub struct JobEvent {
+    pub id: usize,
+    /// The worker the job runs on, none while it's still waiting.
+    pub backend: Option<Backend>,
+    pub state: JobState
+}
+
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: usize,
+    pub kind: JobKind,
+    pub input: PathBuf,
+    pub backend: Option<Backend>,
+    pub state: JobState,
+    /// When a worker picked the job up.
+    pub started_at: Option<Instant>,
+
+    cancel: Arc<AtomicBool>
+}
+
+pub type Work = Box<dyn FnOnce(&Backend, &dyn Progress) -> Result<PathBuf, Error> + Send>;
+
+struct Task {
+    id: usize,
+    cancel: Arc<AtomicBool>,
+    progress: Box<dyn Progress>,
+    work: Work
+}
+
+/// Runs queued jobs on a fixed set of backends, one job per backend at a time. Workers
+/// report every state change over a channel which gets applied to `jobs` by `poll`.
+pub struct Scheduler {
+    backends: Vec<Backend>,
+    jobs: Vec<Job>,
+    next_id: usize,
+    sender: Sender<Task>,
+    events: Receiver<JobEvent>,
+    /// Changes made here rather than by a worker, already applied but still to be handed out.
+    pending: Vec<JobEvent>
+}
+
+impl Scheduler {
+    pub fn new(backends: Vec<Backend>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Task>();
+        let (event_sender, events) = mpsc::channel::<JobEvent>();
+
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        for backend in backends.clone() {
+            let receiver = receiver.clone();
+            let event_sender = event_sender.clone();
+
+            thread::spawn(move || loop {
+                // the lock is only held while waiting so idle workers take turns picking up tasks.
+                let task = match receiver.lock().unwrap().recv() {
+                    Ok(task) => task,
+                    Err(_) => return
+                };
+
+                run_task(task, &backend, &event_sender);
+            });
+        }
+
+        Self {
+            backends,
+            jobs: Vec::new(),
+            next_id: 0,
+            sender,
+            events,
+            pending: Vec::new()
+        }
+    }
+
+    /// Queues `work` to run on the next free backend, returning the job's id.
+    pub fn submit(&mut self, kind: JobKind, input: PathBuf, progress: Box<dyn Progress>, work: Work) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        self.jobs.push(
+            Job {
+                id,
+                kind,
+                input,
+                backend: None,
+                state: JobState::Idle,
+                started_at: None,
+                cancel: cancel.clone()
+            }
+        );
+
+        // workers only stop once the sender is dropped so this can't fail.
+        let _ = self.sender.send(Task { id, cancel, progress, work });
+
+        id
+    }
+
+    /// Applies and returns every state change since the last call, without blocking.
+    pub fn poll(&mut self) -> Vec<JobEvent> {
+        let mut events: Vec<JobEvent> = self.pending.drain(..).collect();
+        let received: Vec<JobEvent> = self.events.try_iter().collect();
+
+        events.extend(received.into_iter().filter(|event| self.apply(event)));
+
+        events
+    }
+
+    /// Blocks until the next state change, none if every job has already finished.
+    pub fn next_event(&mut self) -> Option<JobEvent> {
+        if !self.pending.is_empty() {
+            return Some(self.pending.remove(0));
+        }
+
+        while self.jobs.iter().any(|job| !job.state.finished()) {
+            let event = self.events.recv().ok()?;
+
+            if self.apply(&event) {
+                return Some(event);
+            }
+        }
+
+        None
+    }
+
+    /// Stops a job, straight away if it hasn't started yet or at the next chance it gets if it has.
+    pub fn cancel(&mut self, id: usize) {
+        if let Some(job) = self.jobs.iter_mut().find(|job| job.id == id) {
+            job.cancel.store(true, Ordering::Relaxed);
+
+            // no worker has it to report the change so we do.
+            if let JobState::Idle = job.state {
+                job.state = JobState::Cancelled;
+
+                self.pending.push(JobEvent { id, backend: None, state: JobState::Cancelled });
+            }
+        }
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn backends(&self) -> &[Backend] {
+        &self.backends
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.jobs.retain(|job| !job.state.finished());
+    }
+
+    /// Updates the job the event is for, returning false if it's one we no longer care about.
+    fn apply(&mut self, event: &JobEvent) -> bool {
+        let job = match self.jobs.iter_mut().find(|job| job.id == event.id) {
+            Some(job) => job,
+            None => return false
+        };
+
+        // a job cancelled while it was waiting is already finished as far as we're concerned.
+        if job.state.finished() {
+            return false;
+        }
+
+        if let JobState::Preparing = event.state {
+            job.started_at.get_or_insert_with(Instant::now);
+        }
+
+        job.backend = event.backend.clone();
+        job.state = event.state.clone();
+
+        true
+    }
+}
+
+/// Splits the `[workers]` config into one backend per job that can run at once.
+pub fn backends(kind: BackendKind, cli_path: &Path, workers: &Workers) -> Vec<Backend> {
+    let concurrency = workers.concurrency.max(1);
+
+    // the thread budget is for all of the workers together, not each of them.
+    let threads = match workers.cpu_threads {
+        0 => None,
+        cpu_threads => Some((cpu_threads / concurrency).max(1))
+    };
+
+    (0..concurrency as usize).map(
+        |slot| Backend {
+            slot,
+            kind,
+            cli_path: cli_path.to_path_buf(),
+            gpu_id: match workers.gpu_ids.is_empty() {
+                true => None,
+                false => Some(workers.gpu_ids[slot % workers.gpu_ids.len()])
+            },
+            threads,
+            tile_size: None
+        }
+    ).collect()
+}
+
+/// Passes stages through to the job's own progress while sending the state changes they imply.
+struct JobProgress<'a> {
+    id: usize,
+    backend: &'a Backend,
+    cancel: &'a AtomicBool,
+    skipped: AtomicBool,
+    events: &'a Sender<JobEvent>,
+    inner: &'a dyn Progress
+}
+
+impl JobProgress<'_> {
+    fn send(&self, state: JobState) {
+        let _ = self.events.send(
+            JobEvent {
+                id: self.id,
+                backend: Some(self.backend.clone()),
+                state
+            }
+        );
+    }
+}
+
+impl Progress for JobProgress<'_> {
+    fn stage(&self, stage: Stage, message: String) {
+        self.send(
+            match stage {
+                Stage::Preparing => JobState::Preparing,
+                Stage::Running => JobState::Running { progress: message.clone() },
+                Stage::PostProcessing => JobState::PostProcessing
+            }
+        );
+
+        self.inner.stage(stage, message);
+    }
+
+    fn warning(&self, error: Error) {
+        self.inner.warning(error);
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    fn skipped(&self) {
+        self.skipped.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run_task(task: Task, backend: &Backend, events: &Sender<JobEvent>) {
+    let progress = JobProgress {
+        id: task.id,
+        backend,
+        cancel: &task.cancel,
+        skipped: AtomicBool::new(false),
+        events,
+        inner: task.progress.as_ref()
+    };
+
+    progress.send(JobState::Preparing);
+
+    let result = (task.work)(backend, &progress);
+
+    let state = match (result, progress.cancelled()) {
+        (_, true) => JobState::Cancelled,
+        (Ok(output), false) if progress.skipped.load(Ordering::Relaxed) => JobState::Skipped { output },
+        (Ok(output), false) => JobState::Succeeded { output },
+        (Err(error), false) => JobState::Failed { error }
+    };
+
+    progress.send(state);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+
+    use crate::upscale::BackendKind;
+
+    use super::*;
+
+    struct NoProgress;
+
+    impl Progress for NoProgress {
+        fn stage(&self, _stage: Stage, _message: String) {}
+
+        fn warning(&self, _error: Error) {}
+    }
+
+    #[test]
+    fn cancelling_a_waiting_job_reports_it() {
+        let workers = Workers { concurrency: 1, ..Workers::default() };
+        let mut scheduler = Scheduler::new(backends(BackendKind::Mock, Path::new("mock"), &workers));
+
+        // keeps the only worker busy until we let it go.
+        let (release, wait) = mpsc::channel::<()>();
+
+        let busy = scheduler.submit(
+            JobKind::Upscale,
+            PathBuf::from("busy.png"),
+            Box::new(NoProgress),
+            Box::new(move |_, _| {
+                let _ = wait.recv();
+                Ok(PathBuf::from("busy_out.png"))
+            })
+        );
+        let waiting = scheduler.submit(
+            JobKind::Upscale,
+            PathBuf::from("waiting.png"),
+            Box::new(NoProgress),
+            Box::new(|_, _| Ok(PathBuf::from("waiting_out.png")))
+        );
+
+        scheduler.cancel(waiting);
+
+        let cancelled: Vec<usize> = scheduler.poll().into_iter()
+            .filter(|event| matches!(event.state, JobState::Cancelled))
+            .map(|event| event.id)
+            .collect();
+
+        assert_eq!(cancelled, [waiting]);
+
+        release.send(()).unwrap();
+
+        let mut finished = Vec::new();
+
+        while let Some(event) = scheduler.next_event() {
+            if event.state.finished() {
+                finished.push(event.id);
+            }
+        }
+
+        // the worker still picks the cancelled job up, but it's not reported twice.
+        assert_eq!(finished, [busy]);
+    }
+}
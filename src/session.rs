@@ -0,0 +1,164 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use cirrus_path::v1::get_user_config_dir_path;
+use serde::{Deserialize, Serialize};
+
+use crate::{upscale::UpscaleOptions, APP_NAME};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ItemStatus {
+    /// Queued or running, if the session gets loaded with these left it didn't finish.
+    Pending,
+    Succeeded,
+    Failed(String)
+}
+
+/// One image of the batch along with everything needed to queue it again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionItem {
+    pub id: u64,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// Width and height the output should come out at.
+    pub output_size: (u32, u32),
+    pub options: UpscaleOptions,
+    pub status: ItemStatus
+}
+
+impl SessionItem {
+    /// Whether the output is already there at the size we expect. A write cut short still has the
+    /// right size in its header so the job checks `output_decodes` as well before skipping the item.
+    pub fn output_valid(&self) -> bool {
+        let not_empty = fs::metadata(&self.output).is_ok_and(|metadata| metadata.len() > 0);
+
+        match (not_empty, imagesize::size(&self.output)) {
+            (true, Ok(size)) => (size.width as u32, size.height as u32) == self.output_size,
+            _ => false
+        }
+    }
+}
+
+/// Whether `output` decodes in full. This reads the whole image so it's left to the
+/// job's worker, doing it for every item of a session would hold up the UI.
+pub fn output_decodes(output: &Path) -> bool {
+    image::open(output).is_ok()
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionFile {
+    #[serde(default)]
+    next_id: u64,
+    #[serde(default)]
+    items: Vec<SessionItem>
+}
+
+/// The current batch, written to "session.toml" in the user's config folder on every
+/// change so a batch cut short by a crash or restart can be picked back up.
+#[derive(Debug, Default)]
+pub struct Session {
+    pub items: Vec<SessionItem>,
+    next_id: u64,
+    path: Option<PathBuf>
+}
+
+impl Session {
+    pub fn load() -> Self {
+        let path = match get_user_config_dir_path(APP_NAME) {
+            Ok(config_dir_path) => config_dir_path.join("session.toml"),
+            Err(error) => {
+                log::warn!("Failed to get config path, batches won't be resumable! Error: {}", error.human_message());
+                return Self::default();
+            }
+        };
+
+        let session_file = match fs::read_to_string(&path) {
+            Ok(string) => match toml::from_str::<SessionFile>(&string) {
+                Ok(session_file) => session_file,
+                Err(error) => {
+                    log::warn!("Failed to parse session file, it won't be resumable! Error: {}", error);
+                    SessionFile::default()
+                }
+            },
+            Err(_) => SessionFile::default()
+        };
+
+        Self {
+            items: session_file.items,
+            next_id: session_file.next_id,
+            path: Some(path)
+        }
+    }
+
+    /// Items a previous run never got to finish.
+    pub fn unfinished(&self) -> Vec<SessionItem> {
+        self.items.iter()
+            .filter(|item| item.status == ItemStatus::Pending)
+            .cloned()
+            .collect()
+    }
+
+    /// Adds a pending item, returning its id.
+    pub fn add(&mut self, input: &Path, output: &Path, output_size: (u32, u32), options: &UpscaleOptions) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.items.push(
+            SessionItem {
+                id,
+                input: input.to_path_buf(),
+                output: output.to_path_buf(),
+                output_size,
+                options: options.clone(),
+                status: ItemStatus::Pending
+            }
+        );
+
+        self.save();
+
+        id
+    }
+
+    pub fn set_status(&mut self, id: u64, status: ItemStatus) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.id == id) {
+            item.status = status;
+        }
+
+        // once nothing is left to do the batch is over and there's nothing to resume.
+        if !self.items.iter().any(|item| item.status == ItemStatus::Pending) {
+            self.items.clear();
+        }
+
+        self.save();
+    }
+
+    /// Drops the items with these ids instead of resuming them.
+    pub fn discard(&mut self, ids: &[u64]) {
+        self.items.retain(|item| !ids.contains(&item.id));
+        self.save();
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return
+        };
+
+        if self.items.is_empty() {
+            let _ = fs::remove_file(path);
+            return;
+        }
+
+        let session_file = SessionFile {
+            next_id: self.next_id,
+            items: self.items.clone()
+        };
+
+        let result = toml::to_string(&session_file)
+            .map_err(|error| error.to_string())
+            .and_then(|string| fs::write(path, string).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            log::error!("Failed to save session to '{}'! Error: {}", path.display(), error);
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use std::fmt::Display;
+
+use cirrus_egui::v1::notifier::Notifier;
+use eframe::egui::{self, Key, KeyboardShortcut, ModifierNames, Modifiers};
+use egui_notify::ToastLevel;
+use strum::IntoEnumIterator;
+use strum_macros::EnumIter;
+
+use crate::config::structs::key_binds::KeyBinds;
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter)]
+pub enum Shortcut {
+    AboutBox,
+    OpenImage,
+    NewImage,
+    Upscale,
+    Cancel,
+    NextModel,
+    PreviousModel,
+    ScaleUp,
+    ScaleDown,
+    ToggleCompare,
+    OpenOutputFolder
+}
+
+impl Shortcut {
+    /// The key under `[keybinds]` this shortcut is set with.
+    pub fn config_key(&self) -> &'static str {
+        match self {
+            Shortcut::AboutBox => "about_box",
+            Shortcut::OpenImage => "open_image",
+            Shortcut::NewImage => "new_image",
+            Shortcut::Upscale => "upscale",
+            Shortcut::Cancel => "cancel",
+            Shortcut::NextModel => "next_model",
+            Shortcut::PreviousModel => "previous_model",
+            Shortcut::ScaleUp => "scale_up",
+            Shortcut::ScaleDown => "scale_down",
+            Shortcut::ToggleCompare => "toggle_compare",
+            Shortcut::OpenOutputFolder => "open_output_folder"
+        }
+    }
+
+    pub fn key_bind<'a>(&self, key_binds: &'a KeyBinds) -> &'a str {
+        match self {
+            Shortcut::AboutBox => &key_binds.about_box,
+            Shortcut::OpenImage => &key_binds.open_image,
+            Shortcut::NewImage => &key_binds.new_image,
+            Shortcut::Upscale => &key_binds.upscale,
+            Shortcut::Cancel => &key_binds.cancel,
+            Shortcut::NextModel => &key_binds.next_model,
+            Shortcut::PreviousModel => &key_binds.previous_model,
+            Shortcut::ScaleUp => &key_binds.scale_up,
+            Shortcut::ScaleDown => &key_binds.scale_down,
+            Shortcut::ToggleCompare => &key_binds.toggle_compare,
+            Shortcut::OpenOutputFolder => &key_binds.open_output_folder
+        }
+    }
+
+    pub fn key_bind_mut<'a>(&self, key_binds: &'a mut KeyBinds) -> &'a mut String {
+        match self {
+            Shortcut::AboutBox => &mut key_binds.about_box,
+            Shortcut::OpenImage => &mut key_binds.open_image,
+            Shortcut::NewImage => &mut key_binds.new_image,
+            Shortcut::Upscale => &mut key_binds.upscale,
+            Shortcut::Cancel => &mut key_binds.cancel,
+            Shortcut::NextModel => &mut key_binds.next_model,
+            Shortcut::PreviousModel => &mut key_binds.previous_model,
+            Shortcut::ScaleUp => &mut key_binds.scale_up,
+            Shortcut::ScaleDown => &mut key_binds.scale_down,
+            Shortcut::ToggleCompare => &mut key_binds.toggle_compare,
+            Shortcut::OpenOutputFolder => &mut key_binds.open_output_folder
+        }
+    }
+
+    /// Checks `string` could be this shortcut's bind without clashing with any other in `key_binds`.
+    pub fn validate(&self, string: &str, key_binds: &KeyBinds) -> Result<(), String> {
+        let key_bind = KeyBind::parse(string).ok_or(
+            format!("'{}' isn't a key bind, try something like \"Ctrl+O\".", string)
+        )?;
+
+        let clash = Shortcut::iter()
+            .filter(|other| other != self)
+            .find(|other| KeyBind::parse(other.key_bind(key_binds)) == Some(key_bind));
+
+        match clash {
+            Some(other) => Err(format!("`{}` is already bound to '{}'.", key_bind, other.config_key())),
+            None => Ok(())
+        }
+    }
+}
+
+/// A key with the modifiers that have to be held along with it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyBind(KeyboardShortcut);
+
+impl KeyBind {
+    /// Parses binds like "A", "Ctrl+O" or "Ctrl+Shift+O", "Ctrl" being Cmd on macOS.
+    pub fn parse(string: &str) -> Option<Self> {
+        if string.trim().is_empty() {
+            return None;
+        }
+
+        let mut parts: Vec<&str> = string.split('+').map(str::trim).collect();
+
+        // "Ctrl++" splits into an empty key name, that's the plus key.
+        let key = match parts.pop()? {
+            "" => Key::Plus,
+            name => Key::from_name(name)?
+        };
+
+        let mut modifiers = Modifiers::NONE;
+
+        for part in parts.into_iter().filter(|part| !part.is_empty()) {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" | "command" => Modifiers::COMMAND,
+                "shift" => Modifiers::SHIFT,
+                "alt" | "option" => Modifiers::ALT,
+                _ => return None
+            };
+        }
+
+        Some(Self(KeyboardShortcut::new(modifiers, key)))
+    }
+
+    fn modifier_count(&self) -> usize {
+        let modifiers = self.0.modifiers;
+
+        [modifiers.command, modifiers.shift, modifiers.alt].iter()
+            .filter(|held| **held)
+            .count()
+    }
+}
+
+impl Display for KeyBind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.format(&ModifierNames::NAMES, cfg!(target_os = "macos")))
+    }
+}
+
+/// Every shortcut with the key bind it ended up with.
+pub struct Shortcuts {
+    key_binds: Vec<(Shortcut, KeyBind)>
+}
+
+impl Shortcuts {
+    /// Resolves the config's key binds, falling back to the default for any that are
+    /// invalid and reporting those along with binds used more than once in one toast.
+    /// `reported` holds the problems reported last time, which aren't reported again.
+    pub fn new(key_binds: &KeyBinds, notifier: &Notifier, reported: &mut Vec<String>) -> Self {
+        let (shortcuts, problems) = Self::resolve(key_binds);
+
+        if !problems.is_empty() && problems != *reported {
+            notifier.toast(
+                format!("There's a problem with your key binds!\n{}", problems.join("\n")),
+                ToastLevel::Error,
+                |_| {}
+            );
+        }
+
+        *reported = problems;
+
+        shortcuts
+    }
+
+    /// The shortcuts along with anything wrong with the binds, see `new`.
+    pub fn resolve(key_binds: &KeyBinds) -> (Self, Vec<String>) {
+        let defaults = KeyBinds::default();
+
+        let mut problems = Vec::new();
+        let mut resolved: Vec<(Shortcut, KeyBind)> = Vec::new();
+
+        for shortcut in Shortcut::iter() {
+            let string = shortcut.key_bind(key_binds);
+
+            let key_bind = match KeyBind::parse(string) {
+                Some(key_bind) => key_bind,
+                None => {
+                    let default = shortcut.key_bind(&defaults);
+
+                    problems.push(
+                        format!("'{}' for '{}' is invalid, defaulting to `{}`.", string, shortcut.config_key(), default)
+                    );
+
+                    KeyBind::parse(default).unwrap()
+                }
+            };
+
+            if let Some((other, _)) = resolved.iter().find(|(_, other_bind)| *other_bind == key_bind) {
+                problems.push(
+                    format!("`{}` is bound to both '{}' and '{}'.", key_bind, other.config_key(), shortcut.config_key())
+                );
+            }
+
+            resolved.push((shortcut, key_bind));
+        }
+
+        // binds with more modifiers go first so "Ctrl+Shift+O" isn't taken for "Ctrl+O".
+        resolved.sort_by_key(|(_, key_bind)| std::cmp::Reverse(key_bind.modifier_count()));
+
+        (Self { key_binds: resolved }, problems)
+    }
+
+    /// Shortcuts pressed this frame that `runs` says would do something, only
+    /// their key presses get consumed so the rest are left for egui to handle.
+    pub fn pressed(&self, ctx: &egui::Context, runs: impl Fn(Shortcut) -> bool) -> Vec<Shortcut> {
+        ctx.input_mut(|input| {
+            self.key_binds.iter()
+                .filter(|(shortcut, key_bind)| runs(*shortcut) && input.consume_shortcut(&key_bind.0))
+                .map(|(shortcut, _)| *shortcut)
+                .collect()
+        })
+    }
+
+    pub fn key_bind(&self, shortcut: Shortcut) -> Option<&KeyBind> {
+        self.key_binds.iter()
+            .find(|(other, _)| *other == shortcut)
+            .map(|(_, key_bind)| key_bind)
+    }
+
+    /// Hover text telling what the shortcut is bound to.
+    pub fn hint(&self, shortcut: Shortcut) -> String {
+        match self.key_bind(shortcut) {
+            Some(key_bind) => format!("Shortcut: {}", key_bind),
+            None => String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_keys_with_modifiers() {
+        assert_eq!(KeyBind::parse("A"), Some(KeyBind(KeyboardShortcut::new(Modifiers::NONE, Key::A))));
+        assert_eq!(
+            KeyBind::parse("ctrl + shift + O"),
+            Some(KeyBind(KeyboardShortcut::new(Modifiers::COMMAND | Modifiers::SHIFT, Key::O)))
+        );
+        assert_eq!(KeyBind::parse("Ctrl++"), Some(KeyBind(KeyboardShortcut::new(Modifiers::COMMAND, Key::Plus))));
+
+        assert_eq!(KeyBind::parse(""), None);
+        assert_eq!(KeyBind::parse("Hyper+A"), None);
+        assert_eq!(KeyBind::parse("Ctrl+Nope"), None);
+    }
+
+    #[test]
+    fn defaults_have_no_problems() {
+        let (_, problems) = Shortcuts::resolve(&KeyBinds::default());
+
+        assert!(problems.is_empty(), "{:?}", problems);
+    }
+
+    #[test]
+    fn reports_invalid_and_conflicting_binds() {
+        let key_binds = KeyBinds {
+            upscale: "Ctrl+Nope".to_string(),
+            toggle_compare: "A".to_string(),
+            ..Default::default()
+        };
+
+        let (shortcuts, problems) = Shortcuts::resolve(&key_binds);
+
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("'upscale'"));
+        assert!(problems[1].contains("'about_box' and 'toggle_compare'"));
+
+        assert_eq!(shortcuts.key_bind(Shortcut::Upscale), KeyBind::parse("Ctrl+Enter").as_ref());
+    }
+
+    #[test]
+    fn only_consumes_shortcuts_that_run() {
+        let key_binds = KeyBinds {
+            cancel: "Escape".to_string(),
+            ..Default::default()
+        };
+
+        let (shortcuts, _) = Shortcuts::resolve(&key_binds);
+        let ctx = egui::Context::default();
+
+        let press = |key: Key| egui::RawInput {
+            events: vec![
+                egui::Event::Key {
+                    key,
+                    physical_key: None,
+                    pressed: true,
+                    repeat: false,
+                    modifiers: Modifiers::NONE
+                }
+            ],
+            ..Default::default()
+        };
+
+        let _ = ctx.run(press(Key::Escape), |ctx| {
+            assert!(shortcuts.pressed(ctx, |shortcut| shortcut != Shortcut::Cancel).is_empty());
+            assert!(ctx.input(|input| input.key_pressed(Key::Escape)));
+        });
+
+        let _ = ctx.run(press(Key::Escape), |ctx| {
+            assert_eq!(shortcuts.pressed(ctx, |_| true), [Shortcut::Cancel]);
+            assert!(!ctx.input(|input| input.key_pressed(Key::Escape)));
+        });
+    }
+}
\ No newline at end of file
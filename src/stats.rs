@@ -0,0 +1,130 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use cirrus_path::v1::get_user_config_dir_path;
+use serde::{Deserialize, Serialize};
+
+use crate::{upscale::OutputExt, APP_NAME};
+
+/// Past runs only count this much so the averages follow driver or hardware changes.
+const MAX_SAMPLE_WEIGHT: u32 = 10;
+
+/// A running average that favours recent samples once it has enough of them.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Average {
+    pub value: f64,
+    pub samples: u32
+}
+
+impl Average {
+    fn add(&mut self, sample: f64) {
+        let weight = self.samples.min(MAX_SAMPLE_WEIGHT) as f64;
+
+        self.value = (self.value * weight + sample) / (weight + 1.0);
+        self.samples += 1;
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StatsFile {
+    /// Source pixels upscaled per second, keyed by model name.
+    #[serde(default)]
+    models: HashMap<String, Average>,
+    /// Bytes each output pixel takes up on disk, keyed by output format.
+    #[serde(default)]
+    formats: HashMap<String, Average>
+}
+
+/// How fast each model runs and how big each format ends up on this
+/// machine, stored in "stats.toml" in the user's config folder.
+#[derive(Debug, Default)]
+pub struct Stats {
+    file: StatsFile,
+    path: Option<PathBuf>
+}
+
+impl Stats {
+    pub fn load() -> Self {
+        let path = match get_user_config_dir_path(APP_NAME) {
+            Ok(config_dir_path) => config_dir_path.join("stats.toml"),
+            Err(error) => {
+                log::warn!("Failed to get config path, stats won't be saved! Error: {}", error.human_message());
+                return Self::default();
+            }
+        };
+
+        let file = match fs::read_to_string(&path) {
+            Ok(string) => match toml::from_str::<StatsFile>(&string) {
+                Ok(stats_file) => stats_file,
+                Err(error) => {
+                    log::warn!("Failed to parse stats file, starting a new one! Error: {}", error);
+                    StatsFile::default()
+                }
+            },
+            Err(_) => StatsFile::default()
+        };
+
+        Self {
+            file,
+            path: Some(path)
+        }
+    }
+
+    /// Records a finished upscale of `input_pixels` source pixels that took `seconds`
+    /// and wrote `output_bytes` for `output_pixels` pixels.
+    pub fn record(&mut self, model: &str, output_ext: &OutputExt, input_pixels: u64, output_pixels: u64, seconds: f64, output_bytes: u64) {
+        if seconds > 0.0 {
+            self.file.models.entry(model.to_string())
+                .or_default()
+                .add(input_pixels as f64 / seconds);
+        }
+
+        if output_pixels > 0 {
+            self.file.formats.entry(output_ext.to_string())
+                .or_default()
+                .add(output_bytes as f64 / output_pixels as f64);
+        }
+
+        self.save();
+    }
+
+    /// Seconds `model` should take for `input_pixels` source pixels, if it has been ran before.
+    pub fn estimate_duration(&self, model: &str, input_pixels: u64) -> Option<f64> {
+        match self.file.models.get(model) {
+            Some(average) if average.value > 0.0 => Some(input_pixels as f64 / average.value),
+            _ => None
+        }
+    }
+
+    /// Rough file size of an image with `output_pixels` pixels. Formats we have
+    /// no runs for yet fall back to typical bytes per pixel for photos.
+    pub fn estimate_file_size(&self, output_ext: &OutputExt, output_pixels: u64) -> u64 {
+        let bytes_per_pixel = match self.file.formats.get(&output_ext.to_string()) {
+            Some(average) if average.samples > 0 => average.value,
+            _ => match output_ext {
+                OutputExt::PNG => 2.0,
+                OutputExt::TIFF => 4.0,
+                OutputExt::GIF => 1.0,
+                OutputExt::WebP => 0.4,
+                OutputExt::JPG => 0.5,
+                OutputExt::AVIF => 0.15
+            }
+        };
+
+        (output_pixels as f64 * bytes_per_pixel) as u64
+    }
+
+    fn save(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return
+        };
+
+        let result = toml::to_string(&self.file)
+            .map_err(|error| error.to_string())
+            .and_then(|string| fs::write(path, string).map_err(|error| error.to_string()));
+
+        if let Err(error) = result {
+            log::error!("Failed to save stats to '{}'! Error: {}", path.display(), error);
+        }
+    }
+}
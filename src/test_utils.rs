@@ -0,0 +1,40 @@
+//! Helpers shared by the unit tests and the tests in "tests/", which pull this file in by its path.
+
+use std::{fs, ops::Deref, path::{Path, PathBuf}};
+
+/// A fresh folder for a test to put its files in, it's deleted again once dropped.
+pub struct TestFolder(PathBuf);
+
+impl TestFolder {
+    pub fn new(name: &str) -> Self {
+        let folder = std::env::temp_dir()
+            .join("aeternum-tests")
+            .join(format!("{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        Self(folder)
+    }
+}
+
+impl Deref for TestFolder {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestFolder {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Writes a gradient PNG (or whatever format the extension says) to `path`.
+pub fn write_image(path: &Path, width: u32, height: u32) {
+    let image = image::RgbImage::from_fn(width, height, |x, y| image::Rgb([(x * 16) as u8, (y * 16) as u8, 128]));
+
+    image.save(path).unwrap();
+}
@@ -0,0 +1,223 @@
+use std::{fs, path::{Path, PathBuf}};
+
+use cirrus_egui::v1::styling::Styling;
+use cirrus_path::v1::get_user_config_dir_path;
+use cirrus_theming::v1::{Colour, Theme};
+use eframe::egui::{self, Style, ThemePreference};
+use serde::Deserialize;
+
+use crate::{config::structs::theme::ThemeConfig, error::Error, APP_NAME};
+
+/// A theme file from the "themes" config folder, e.g. "themes/rose.toml" for the theme "rose".
+#[derive(Debug, Deserialize)]
+struct ThemeFile {
+    /// Leave it out to follow the OS's dark/light preference.
+    dark: Option<bool>,
+    accent_colour: Option<String>,
+    /// The primary, secondary, third and text colours.
+    #[serde(default)]
+    palette: Vec<String>
+}
+
+/// What our theme gets built from, the OS's preference decides whether it's dark when `is_dark` isn't set.
+#[derive(Debug, Clone, Default)]
+pub struct Theming {
+    is_dark: Option<bool>,
+    palette: Vec<u32>,
+    accent_colour: Option<u32>
+}
+
+impl Theming {
+    /// Resolves a theme name ("system", "dark", "light" or a theme file's) with
+    /// the config's accent colour, which wins over the theme file's own.
+    pub fn load(name: &str, config: &ThemeConfig) -> Result<Self, Error> {
+        let mut theming = match name {
+            "system" => Self::default(),
+            "dark" => Self { is_dark: Some(true), ..Default::default() },
+            "light" => Self { is_dark: Some(false), ..Default::default() },
+            name => match theme_file_path(name) {
+                Some(path) if path.is_file() => Self::from_file(name, &path)?,
+                _ => return Err(
+                    Error::FailedToLoadTheme(None, name.to_string(), no_theme_reason(name))
+                )
+            }
+        };
+
+        if !config.accent_colour.is_empty() {
+            theming.accent_colour = Some(
+                parse_colour(&config.accent_colour).ok_or_else(
+                    || Error::FailedToLoadTheme(None, name.to_string(), invalid_colour_reason(&config.accent_colour))
+                )?
+            );
+        }
+
+        Ok(theming)
+    }
+
+    fn from_file(name: &str, path: &Path) -> Result<Self, Error> {
+        let to_error = |actual_error: String, reason: String| Error::FailedToLoadTheme(
+            Some(actual_error), name.to_string(), reason
+        );
+
+        let string = fs::read_to_string(path)
+            .map_err(|error| to_error(error.to_string(), format!("Failed to read '{}'.", path.display())))?;
+
+        let file = toml::from_str::<ThemeFile>(&string)
+            .map_err(|error| to_error(error.to_string(), error.message().to_string()))?;
+
+        // the theme needs every colour of the palette or none of them.
+        if !file.palette.is_empty() && file.palette.len() != 4 {
+            return Err(
+                Error::FailedToLoadTheme(
+                    None,
+                    name.to_string(),
+                    "The palette needs four colours: primary, secondary, third and text.".to_string()
+                )
+            );
+        }
+
+        let colours = file.accent_colour.iter().chain(file.palette.iter())
+            .map(|string| parse_colour(string).ok_or_else(
+                || Error::FailedToLoadTheme(None, name.to_string(), invalid_colour_reason(string))
+            ))
+            .collect::<Result<Vec<u32>, Error>>()?;
+
+        let (accent_colour, palette) = match file.accent_colour {
+            Some(_) => (colours.first().copied(), colours[1..].to_vec()),
+            None => (None, colours)
+        };
+
+        Ok(Self { is_dark: file.dark, palette, accent_colour })
+    }
+
+    pub fn follows_system(&self) -> bool {
+        self.is_dark.is_none()
+    }
+
+    /// The theme to use, `system_is_dark` being the OS's preference if we know it yet.
+    pub fn theme(&self, system_is_dark: Option<bool>) -> Theme {
+        Theme::new(
+            self.is_dark.or(system_is_dark).unwrap_or(true),
+            self.palette.iter().map(|hex| Colour::from_hex(*hex)).collect(),
+            self.accent_colour.map(Colour::from_hex)
+        )
+    }
+}
+
+/// Checks `name` is a theme we can load, for the settings.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    match name {
+        "system" | "dark" | "light" => Ok(()),
+        name => match theme_file_path(name) {
+            Some(path) if path.is_file() => Ok(()),
+            _ => Err(no_theme_reason(name))
+        }
+    }
+}
+
+/// Parses a hex code like "#e05f78".
+pub fn parse_colour(string: &str) -> Option<u32> {
+    let hex = string.trim().trim_start_matches('#');
+
+    match hex.len() == 6 {
+        true => u32::from_str_radix(hex, 16).ok(),
+        false => None
+    }
+}
+
+/// Styles the app with `theme`, this is done again whenever the theme changes.
+pub fn apply(ctx: &egui::Context, theme: &Theme) {
+    // pin egui to our theme otherwise it swaps in its own style when the OS's preference changes.
+    ctx.set_theme(
+        match theme.is_dark {
+            true => ThemePreference::Dark,
+            false => ThemePreference::Light
+        }
+    );
+
+    let mut custom_style = Style {..Default::default()};
+
+    custom_style.spacing.slider_width = 180.0;
+
+    Styling::new(theme, Some(custom_style))
+        .set_all()
+        .apply(ctx);
+}
+
+fn theme_file_path(name: &str) -> Option<PathBuf> {
+    match get_user_config_dir_path(APP_NAME) {
+        Ok(config_dir_path) => Some(config_dir_path.join("themes").join(format!("{}.toml", name))),
+        Err(error) => {
+            log::warn!("Failed to get the config path to look for themes in! Error: {}", error.human_message());
+            None
+        }
+    }
+}
+
+fn no_theme_reason(name: &str) -> String {
+    format!(
+        "It isn't \"system\", \"dark\" or \"light\" and there's no '{}.toml' in the \"themes\" config folder.", name
+    )
+}
+
+fn invalid_colour_reason(string: &str) -> String {
+    format!("'{}' isn't a colour, try a hex code like \"#e05f78\".", string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_colours() {
+        assert_eq!(parse_colour("#e05f78"), Some(0xe05f78));
+        assert_eq!(parse_colour("E05F78"), Some(0xe05f78));
+
+        assert_eq!(parse_colour("#e05f7"), None);
+        assert_eq!(parse_colour("#e05f7z"), None);
+        assert_eq!(parse_colour("rose"), None);
+    }
+
+    #[test]
+    fn loads_a_theme_file() {
+        let path = std::env::temp_dir().join(format!("aeternum-theme-{}.toml", std::process::id()));
+
+        fs::write(
+            &path,
+            "dark = false\naccent_colour = \"#e05f78\"\npalette = [\"#ffffff\", \"#eeeeee\", \"#dddddd\", \"#111111\"]\n"
+        ).unwrap();
+
+        let theming = Theming::from_file("rose", &path).unwrap();
+
+        assert_eq!(theming.is_dark, Some(false));
+        assert_eq!(theming.accent_colour, Some(0xe05f78));
+        assert_eq!(theming.palette, [0xffffff, 0xeeeeee, 0xdddddd, 0x111111]);
+
+        fs::write(&path, "palette = [\"#ffffff\"]\n").unwrap();
+
+        assert_eq!(Theming::from_file("rose", &path).unwrap_err().kind(), "FailedToLoadTheme");
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn config_accent_colour_wins() {
+        let config = ThemeConfig {
+            name: "light".to_string(),
+            accent_colour: "#3a8fd9".to_string()
+        };
+
+        let theming = Theming::load(&config.name, &config).unwrap();
+
+        assert!(!theming.follows_system());
+        assert_eq!(theming.accent_colour, Some(0x3a8fd9));
+
+        let config = ThemeConfig {
+            accent_colour: "blue".to_string(),
+            ..Default::default()
+        };
+
+        assert!(Theming::load(&config.name, &config).is_err());
+        assert!(Theming::load("no-such-theme", &ThemeConfig::default()).is_err());
+    }
+}
\ No newline at end of file
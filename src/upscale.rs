@@ -1,22 +1,30 @@
-use std::{io::{BufRead, BufReader}, path::PathBuf, process::Stdio, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+use std::{fs, io::{BufRead, BufReader}, path::{Path, PathBuf}, process::Stdio, sync::{Arc, Mutex, OnceLock}, time::{Duration, Instant}};
 use cirrus_egui::v1::notifier::Notifier;
 use egui_notify::ToastLevel;
 use std::process::Command;
-use strum_macros::{EnumIter, Display};
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
 
-use crate::{error::Error, image::Image};
+use crate::{config::structs::{backend::BackendPaths, defaults::Defaults, workers::Workers}, error::Error, history::{self, History, HistoryEntry, JobStatus}, image::{self, Image}, mock, scheduler::{self, Job, JobEvent, JobKind, JobState, Scheduler, Work}, session::{self, ItemStatus, Session, SessionItem}, stats::Stats, processing::{alpha::{self, AlphaStrategy}, animation, crop::Crop, encode::{self, QualityOptions}, metadata::{Metadata, MetadataPolicy}, temp_path, tiling::{self, TilingOptions}}};
 
-#[derive(Clone, PartialEq, EnumIter, Display)]
+#[derive(Debug, Clone, PartialEq, EnumIter, Display, EnumString, Serialize, Deserialize)]
+#[strum(ascii_case_insensitive)]
 pub enum OutputExt {
     #[strum(to_string = "WebP")]
     WebP,
     #[strum(to_string = "PNG")]
     PNG,
     #[strum(to_string = "JPG")]
-    JPG
+    JPG,
+    #[strum(to_string = "AVIF")]
+    AVIF,
+    #[strum(to_string = "TIFF")]
+    TIFF,
+    #[strum(to_string = "GIF")]
+    GIF
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Model {
     path: PathBuf,
     folder: PathBuf,
@@ -24,40 +32,55 @@ pub struct Model {
     pub name: String
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpscaleOptions {
     pub scale: i8,
-    pub compression: i32,
+    pub quality: QualityOptions,
     pub model: Option<Model>,
     pub output_ext: OutputExt,
-    pub output: Option<PathBuf>
+    pub output: Option<PathBuf>,
+    pub crop: Option<Crop>,
+    pub metadata: MetadataPolicy,
+    pub alpha: AlphaStrategy,
+    pub tiling: TilingOptions
 }
 
 pub struct Upscale {
     pub options: UpscaleOptions,
-    pub upscaling: bool,
     pub models: Vec<Model>,
+    pub history: Arc<Mutex<History>>,
+    pub stats: Arc<Mutex<Stats>>,
+    pub session: Arc<Mutex<Session>>,
+    /// The image the last successful upscale wrote.
+    pub output: Option<PathBuf>,
+    /// What the workers the scheduler runs jobs on were set up from.
+    pub workers: Workers,
 
     models_folder: PathBuf,
+    backend_kind: BackendKind,
     cli_path: PathBuf,
-    upscaling_arc: Arc<Mutex<bool>>
+    scheduler: Scheduler
 }
 
 impl Default for UpscaleOptions {
     fn default() -> Self {
         Self {
             scale: 4,
-            compression: 0,
+            quality: QualityOptions::default(),
             model: None,
             output_ext: OutputExt::PNG,
-            output: None
+            output: None,
+            crop: None,
+            metadata: MetadataPolicy::KeepAll,
+            alpha: AlphaStrategy::Model,
+            tiling: TilingOptions::default()
         }
     }
 }
 
 impl Upscale {
     #[cfg(feature = "package")]
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(paths: &BackendPaths) -> Result<Self, Error> {
         use std::env;
 
         let executable_path = match env::current_exe() {
@@ -65,7 +88,9 @@ impl Upscale {
             Err(error) => return Err(Error::FailedToGetCurrentExecutablePath(Some(error.to_string())))
         };
         
-        let tool_path = if cfg!(unix) {
+        let tool_path = if !paths.upscayl_bin.is_empty() {
+            PathBuf::from(&paths.upscayl_bin)
+        } else if cfg!(unix) {
             executable_path.with_file_name("upscayl-bin")
         } else {
             executable_path.with_file_name("upscayl-bin.exe")
@@ -75,7 +100,10 @@ impl Upscale {
             return Err(Error::UpscaylNotInPath(Some("upscayl-bin is not with the aeternum executable.".to_string())))
         }
 
-        let models_folder = executable_path.with_file_name("models");
+        let models_folder = match paths.models_folder.is_empty() {
+            true => executable_path.with_file_name("models"),
+            false => PathBuf::from(&paths.models_folder)
+        };
 
         if !models_folder.exists() {
             return Err(Error::ModelsFolderNotFound(Some("Folder does not exist.".to_string()), models_folder))
@@ -83,22 +111,35 @@ impl Upscale {
 
         return Ok(Self {
             options: UpscaleOptions::default(),
-            upscaling: false,
             models: Vec::new(),
+            history: Arc::new(Mutex::new(History::load())),
+            stats: Arc::new(Mutex::new(Stats::load())),
+            session: Arc::new(Mutex::new(Session::load())),
+            output: None,
 
             models_folder,
-            cli_path: tool_path,
-            upscaling_arc: Arc::new(false.into())
+            backend_kind: BackendKind::Upscayl,
+            scheduler: Scheduler::new(scheduler::backends(BackendKind::Upscayl, &tool_path, &Workers::default())),
+            workers: Workers::default(),
+            cli_path: tool_path
         })
     }
 
     #[cfg(not(feature = "package"))] // NOTE: This only works on linux.
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(paths: &BackendPaths) -> Result<Self, Error> {
         use which::which;
 
-        match which("upscayl-bin") {
+        let upscayl_bin = match paths.upscayl_bin.is_empty() {
+            true => "upscayl-bin",
+            false => paths.upscayl_bin.as_str()
+        };
+
+        match which(upscayl_bin) {
             Ok(path) => {
-                let models_folder = PathBuf::from("/usr/lib/upscayl/models");
+                let models_folder = match paths.models_folder.is_empty() {
+                    true => PathBuf::from("/usr/lib/upscayl/models"),
+                    false => PathBuf::from(&paths.models_folder)
+                };
 
                 if !models_folder.exists() {
                     return Err(Error::ModelsFolderNotFound(Some("Folder doesn't exist".to_string()), models_folder))
@@ -106,19 +147,59 @@ impl Upscale {
 
                 Ok(Self {
                     options: UpscaleOptions::default(),
-                    upscaling: false,
                     models: Vec::new(),
+                    history: Arc::new(Mutex::new(History::load())),
+                    stats: Arc::new(Mutex::new(Stats::load())),
+                    session: Arc::new(Mutex::new(Session::load())),
+                    output: None,
 
                     models_folder,
-                    cli_path: path,
-                    upscaling_arc: Arc::new(false.into())
+                    backend_kind: BackendKind::Upscayl,
+                    scheduler: Scheduler::new(scheduler::backends(BackendKind::Upscayl, &path, &Workers::default())),
+                    workers: Workers::default(),
+                    cli_path: path
                 })
             },
             Err(err) => Err(Error::UpscaylNotInPath(Some(err.to_string())))
         }
     }
 
+    /// An `Upscale` on the mock backend with its fake models, for testing without 
+    /// upscayl-bin. Nothing it does gets saved to the history, stats or session.
+    pub fn mock() -> Self {
+        let models_folder = PathBuf::from("mock");
+        let cli_path = PathBuf::from("mock");
+
+        let models = mock::MODELS.iter().map(
+            |name| Model {
+                path: models_folder.join(format!("{}.bin", name)),
+                folder: models_folder.clone(),
+                name: name.to_string()
+            }
+        ).collect();
+
+        Self {
+            options: UpscaleOptions::default(),
+            models,
+            history: Arc::new(Mutex::new(History::default())),
+            stats: Arc::new(Mutex::new(Stats::default())),
+            session: Arc::new(Mutex::new(Session::default())),
+            output: None,
+
+            models_folder,
+            backend_kind: BackendKind::Mock,
+            scheduler: Scheduler::new(scheduler::backends(BackendKind::Mock, &cli_path, &Workers::default())),
+            workers: Workers::default(),
+            cli_path
+        }
+    }
+
     pub fn init(&mut self, enabled: bool) -> Result<(), Error> {
+        // the mock backend comes with its own models.
+        if self.backend_kind == BackendKind::Mock {
+            return Ok(());
+        }
+
         if enabled {
             let path: PathBuf = dirs::config_local_dir().unwrap().join("cloudy").join("aeternum").join("models");
 
@@ -138,160 +219,214 @@ impl Upscale {
         Ok(())
     }
 
-    pub fn update(&mut self) {
-        if let Ok(value) = self.upscaling_arc.try_lock() {
-            self.upscaling = value.clone();
+    /// Swaps the scheduler for one with a backend per worker in the config. Jobs 
+    /// already queued still finish but they won't show up in `jobs` anymore.
+    pub fn set_workers(&mut self, workers: &Workers) {
+        self.scheduler = Scheduler::new(scheduler::backends(self.backend_kind, &self.cli_path, workers));
+        self.workers = workers.clone();
+    }
+
+    /// Sets the options to the config's defaults, erroring if there's no model by the default's name.
+    pub fn apply_defaults(&mut self, defaults: &Defaults) -> Result<(), Error> {
+        self.options.scale = defaults.scale.clamp(1, 16);
+
+        match defaults.format.parse::<OutputExt>() {
+            Ok(output_ext) => self.options.output_ext = output_ext,
+            Err(_) => log::warn!("'{}' isn't a format we can save as, ignoring it.", defaults.format)
+        }
+
+        match defaults.metadata.parse::<MetadataPolicy>() {
+            Ok(policy) => self.options.metadata = policy,
+            Err(_) => log::warn!("'{}' isn't a metadata policy, ignoring it.", defaults.metadata)
+        }
+
+        // no output folder or model in the defaults means none, not whatever was used last.
+        self.options.output = match defaults.output_folder.is_empty() {
+            true => None,
+            false => Some(PathBuf::from(&defaults.output_folder))
+        };
+
+        self.options.model = None;
+
+        if defaults.model.is_empty() {
+            return Ok(());
+        }
+
+        match self.models.iter().find(|model| model.name == defaults.model) {
+            Some(model) => self.options.model = Some(model.clone()),
+            None => return Err(Error::ModelNotFound(None, defaults.model.clone()))
         }
+
+        Ok(())
     }
 
-    pub fn reset_options(&mut self) {
-        self.options = UpscaleOptions::default();
+    /// Applies every job state change since the last call and returns them, also storing
+    /// the output of any job that succeeded in `output` as long as it was for `image`.
+    /// Jobs for an image that's no longer open would otherwise show their output for the new one.
+    pub fn update(&mut self, image: Option<&Path>) -> Vec<JobEvent> {
+        let events = self.scheduler.poll();
+
+        for event in events.iter() {
+            if let JobState::Succeeded { output } = &event.state {
+                let current = self.job(event.id)
+                    .is_some_and(|job| job.kind == JobKind::Upscale && Some(job.input.as_path()) == image);
+
+                if current {
+                    self.output = Some(output.clone());
+                }
+            }
+        }
+
+        events
+    }
+
+    /// Blocks until a job changes state, none once every job has finished.
+    pub fn next_event(&mut self) -> Option<JobEvent> {
+        self.scheduler.next_event()
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        self.scheduler.jobs()
+    }
+
+    pub fn job(&self, id: usize) -> Option<&Job> {
+        self.scheduler.jobs().iter().find(|job| job.id == id)
+    }
+
+    /// Whether no job is waiting or running.
+    pub fn idle(&self) -> bool {
+        self.jobs().iter().all(|job| job.state.finished())
+    }
+
+    /// Whether every backend already has a job, so anything queued now would have to wait.
+    pub fn busy(&self) -> bool {
+        let active = self.jobs().iter()
+            .filter(|job| !job.state.finished())
+            .count();
+
+        active >= self.scheduler.backends().len()
+    }
+
+    pub fn cancel(&mut self, id: usize) {
+        self.scheduler.cancel(id);
+    }
+
+    pub fn clear_finished_jobs(&mut self) {
+        self.scheduler.clear_finished();
     }
 
-    fn upscaling_reset(&mut self) {
-        self.upscaling = false;
-        self.upscaling_arc = Arc::new(false.into());
+    pub fn reset_options(&mut self) {
+        self.options = UpscaleOptions::default();
     }
 
     pub fn upscale(&mut self, image: Image, notifier: &Notifier) {
-        self.upscaling_reset();
+        self.queue(image, Box::new(notifier.clone()));
+    }
 
-        let path = &image.path;
+    /// Queues `image` with the current options on the scheduler, returning the job's id.
+    pub fn queue(&mut self, image: Image, progress: Box<dyn Progress>) -> usize {
+        let out = self.output_path(&image);
+        let options = self.options.clone();
 
-        let output_folder = match &self.options.output {
-            Some(path) => path.clone(),
-            None => image.path.parent().unwrap().to_path_buf()
+        let (width, height) = match &options.crop {
+            Some(crop) => (crop.width, crop.height),
+            None => (image.image_size.width as u32, image.image_size.height as u32)
         };
+        let scale = options.scale as u32;
 
-        let out = output_folder.join(
-            image.create_output(&self.options)
+        let session_id = self.session.lock().unwrap().add(
+            &image.path, &out, (width * scale, height * scale), &options
         );
 
-        let path = path.clone();
-        let cli_path = self.cli_path.clone();
-        let upscaling_arc = self.upscaling_arc.clone();
-        let notifier_arc = notifier.clone();
-        let options = self.options.clone();
-
-        let mut upscaling = self.upscaling_arc.lock().unwrap();
-        *upscaling = true;
+        self.submit(image, out, options, session_id, false, progress)
+    }
 
-        let upscale_stuff = move || {
-            let now = Instant::now();
+    /// Queues an item left over from a batch that didn't finish, returning the job's id. If its
+    /// output looks to be there already the job ends up `Skipped` once it's made sure it decodes.
+    pub fn requeue(&mut self, item: SessionItem, progress: Box<dyn Progress>) -> Result<usize, Error> {
+        let image = match Image::from_path(item.input.clone()) {
+            Ok(image) => image,
+            Err(error) => {
+                self.session.lock().unwrap().set_status(item.id, ItemStatus::Failed(error.to_string()));
+                return Err(error);
+            }
+        };
 
-            notifier_arc.set_loading(Some("Initializing command...".into()));
+        let skip_if_done = item.output_valid();
 
-            let mut upscale_command = Command::new(cli_path.to_string_lossy().to_string());
+        Ok(self.submit(image, item.output, item.options, item.id, skip_if_done, progress))
+    }
 
-            #[cfg(target_os = "windows")] {
-                use std::os::windows::process::CommandExt;
+    /// Requeues everything the last session didn't finish, toasting anything that couldn't be.
+    pub fn resume_session(&mut self, notifier: &Notifier) {
+        let items = self.session.lock().unwrap().unfinished();
 
-                upscale_command.creation_flags(0x08000000);
+        for item in items {
+            if let Err(error) = self.requeue(item, Box::new(notifier.clone())) {
+                notifier.toast(
+                    Box::new(error),
+                    ToastLevel::Error,
+                    |_| {}
+                );
             }
+        }
+    }
 
-            let model = &options.model.unwrap();
-
-            let cmd = upscale_command
-                .args([
-                    "-i",
-                    &path.to_string_lossy(),
-                    "-o",
-                    &out.to_string_lossy(),
-                    "-m",
-                    &model.folder.to_string_lossy(),
-                    "-n",
-                    &model.name,
-                    "-s",
-                    &options.scale.to_string(),
-                    "-c",
-                    &options.compression.to_string()
-                ])
-                .stderr(Stdio::piped()) // why do you output to stderr :woe: ~ Ananas
-                .spawn();
-
-            match cmd {
-                Ok(mut child) => {
-                    if let Some(stderr) = child.stderr.take() {
-                        let reader = BufReader::new(stderr);
-
-                        for line in reader.lines() {
-                            match line {
-                                Ok(output) => {
-                                    let out_bytes = output.as_bytes();
-
-                                    if !out_bytes.is_empty() && out_bytes[0].is_ascii_digit() {
-                                        notifier_arc.set_loading(Some(format!("Processing: {}", output)));
-                                    }
-                                },
-                                _ => {}
-                            }
-                        }
-                    }
+    fn submit(
+        &mut self,
+        image: Image,
+        out: PathBuf,
+        options: UpscaleOptions,
+        session_id: u64,
+        skip_if_done: bool,
+        progress: Box<dyn Progress>
+    ) -> usize {
+        let runner = self.runner();
+        let session = self.session.clone();
+        let input = image.path.clone();
 
-                    let status = child.wait_with_output();
-
-                    match status {
-                        Ok(status) => {
-                            if status.status.success() {
-                                let upscale_time = now.elapsed().as_secs();
-
-                                notifier_arc.toast(
-                                    format!("Successfully upscaled image in {} seconds!", upscale_time),
-                                    ToastLevel::Success,
-                                    |toast| {
-                                        toast.duration(Some(Duration::from_secs(10)));
-                                    }
-                                );
-                            } else {
-                                let error = Error::FailedToUpscaleImage(
-                                    None,
-                                    "Process returned as not successful.".to_string()
-                                );
-                                notifier_arc.toast(
-                                    Box::new(error),
-                                    ToastLevel::Error,
-                                    |toast| {
-                                        toast.duration(Some(Duration::from_secs(10)));
-                                    }
-                                );
-                            }
-                        },
-                        Err(error) => {
-                            let error = Error::FailedToUpscaleImage(
-                                Some(error.to_string()),
-                                "Failed to wait for process.".to_string()
-                            );
-
-                            notifier_arc.toast(
-                                Box::new(error),
-                                ToastLevel::Error,
-                                |toast| {
-                                    toast.duration(Some(Duration::from_secs(10)));
-                                }
-                            );
-                        }
+        self.scheduler.submit(
+            JobKind::Upscale,
+            input,
+            progress,
+            Box::new(move |backend, progress| {
+                let result = if progress.cancelled() {
+                    Err(cancelled_error())
+                } else if skip_if_done && session::output_decodes(&out) {
+                    progress.skipped();
+                    Ok(())
+                } else {
+                    runner.run(backend, &image, &out, &options, progress)
+                };
+
+                session.lock().unwrap().set_status(
+                    session_id,
+                    match &result {
+                        Ok(_) => ItemStatus::Succeeded,
+                        Err(error) => ItemStatus::Failed(error.to_string())
                     }
-                },
-                Err(error) => {
-                    let error = Error::FailedToUpscaleImage(Some(error.to_string()), "Failed to spawn child process.".to_string());
-
-                    notifier_arc.toast(
-                        Box::new(error),
-                        ToastLevel::Error,
-                        |toast| {
-                            toast.duration(Some(Duration::from_secs(10)));
-                        }
-                    );
-                }
-            }
+                );
 
-            notifier_arc.unset_loading();
+                result.map(|_| out)
+            })
+        )
+    }
 
-            let mut upscaling = upscaling_arc.lock().unwrap();
-            *upscaling = false;
-        };
+    /// Queues work other than an upscale on the scheduler, returning the job's id.
+    /// It waits for a free worker like any upscale rather than fighting them for the GPU.
+    pub fn queue_work(&mut self, kind: JobKind, input: PathBuf, progress: Box<dyn Progress>, work: Work) -> usize {
+        self.scheduler.submit(kind, input, progress, work)
+    }
+
+    pub fn output_path(&self, image: &Image) -> PathBuf {
+        output_path(&image.path, image.animated, &self.options)
+    }
 
-        thread::spawn(upscale_stuff);
+    fn runner(&self) -> JobRunner {
+        JobRunner {
+            history: self.history.clone(),
+            stats: self.stats.clone()
+        }
     }
 
     fn get_models(&mut self, folder_path: PathBuf) {
@@ -327,3 +462,650 @@ impl Upscale {
         }
     }
 }
+
+/// What a job ends up with (besides `JobState::Cancelled`) when it gets cancelled.
+fn cancelled_error() -> Error {
+    Error::FailedToUpscaleImage(None, "The job was cancelled.".to_string())
+}
+
+/// Which part of the job a stage message is about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    Preparing,
+    /// upscayl-bin is running.
+    Running,
+    PostProcessing
+}
+
+/// Where a job reports what it's currently doing.
+pub trait Progress: Send + Sync {
+    fn stage(&self, stage: Stage, message: String);
+    /// Something went wrong that didn't stop the job.
+    fn warning(&self, error: Error);
+    /// Whether the job should stop at the next chance it gets.
+    fn cancelled(&self) -> bool {
+        false
+    }
+    /// The job found its output already done so it won't run.
+    fn skipped(&self) {}
+}
+
+impl Progress for Notifier {
+    fn stage(&self, _stage: Stage, message: String) {
+        self.set_loading(Some(message));
+    }
+
+    fn warning(&self, error: Error) {
+        self.toast(
+            Box::new(error),
+            ToastLevel::Warning,
+            |toast| {
+                toast.duration(Some(Duration::from_secs(10)));
+            }
+        );
+    }
+}
+
+/// Everything a job needs from `Upscale` so it can run off the UI thread.
+#[derive(Clone)]
+struct JobRunner {
+    history: Arc<Mutex<History>>,
+    stats: Arc<Mutex<Stats>>
+}
+
+impl JobRunner {
+    /// Runs the pipeline, cleans up after it and records the job in the history and stats.
+    fn run(
+        &self,
+        backend: &Backend,
+        image: &Image,
+        out: &Path,
+        options: &UpscaleOptions,
+        progress: &dyn Progress
+    ) -> Result<(), Error> {
+        let now = Instant::now();
+
+        let mut temp_files = Vec::new();
+
+        let result = run_pipeline(
+            backend, image, out, options, progress, &mut temp_files
+        );
+
+        for temp_file in temp_files {
+            let _ = fs::remove_file(temp_file);
+        }
+
+        let status = match &result {
+            Ok(_) => JobStatus::Succeeded,
+            Err(error) => JobStatus::Failed(error.to_string())
+        };
+
+        self.history.lock().unwrap().push(
+            HistoryEntry {
+                input: image.path.clone(),
+                output: out.to_path_buf(),
+                finished_at: history::unix_timestamp(),
+                duration_secs: now.elapsed().as_secs_f64(),
+                status,
+                backend_version: backend_version(&backend.cli_path),
+                options: options.clone()
+            }
+        );
+
+        // frame counts aren't known up front so animations would skew the estimates.
+        if result.is_ok() && !image.animated {
+            let input_pixels = input_pixels(image, options);
+            let output_bytes = fs::metadata(out).map(|metadata| metadata.len()).unwrap_or(0);
+
+            self.stats.lock().unwrap().record(
+                &options.model.as_ref().unwrap().name,
+                &options.output_ext,
+                input_pixels,
+                input_pixels * (options.scale as u64).pow(2),
+                now.elapsed().as_secs_f64(),
+                output_bytes
+            );
+        }
+
+        result
+    }
+}
+
+/// How many source pixels get upscaled, which is only the cropped region if there is one.
+pub fn input_pixels(image: &Image, options: &UpscaleOptions) -> u64 {
+    match &options.crop {
+        Some(crop) => crop.width as u64 * crop.height as u64,
+        None => image.image_size.width as u64 * image.image_size.height as u64
+    }
+}
+
+/// Where the upscale of the image at `path` gets written to.
+pub fn output_path(path: &Path, animated: bool, options: &UpscaleOptions) -> PathBuf {
+    let output_folder = match &options.output {
+        Some(folder) => folder.clone(),
+        None => path.parent().unwrap().to_path_buf()
+    };
+
+    output_folder.join(image::output_name(path, animated, options))
+}
+
+/// Best effort at finding out which upscayl-bin we're running, for the history.
+/// It's only looked up once and not until the first job actually needs it.
+fn backend_version(cli_path: &Path) -> String {
+    static BACKEND_VERSION: OnceLock<String> = OnceLock::new();
+
+    BACKEND_VERSION.get_or_init(|| detect_backend_version(cli_path)).clone()
+}
+
+fn detect_backend_version(cli_path: &Path) -> String {
+    let mut command = Command::new(cli_path);
+
+    #[cfg(target_os = "windows")] {
+        use std::os::windows::process::CommandExt;
+
+        command.creation_flags(0x08000000);
+    }
+
+    // upscayl-bin has no version flag but some builds mention it in their usage text.
+    let output = match command.arg("-h").output() {
+        Ok(output) => output,
+        Err(_) => return "unknown".to_string()
+    };
+
+    let text = format!(
+        "{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr)
+    );
+
+    text.lines()
+        .find(|line| line.to_lowercase().contains("version"))
+        .map(|line| line.trim().to_string())
+        .unwrap_or("unknown".to_string())
+}
+
+/// Everything that happens to an image from the source file to the 
+/// final output. Intermediate files are pushed to `temp_files` for clean up.
+fn run_pipeline(
+    backend: &Backend,
+    image: &Image,
+    out: &Path,
+    options: &UpscaleOptions,
+    progress: &dyn Progress,
+    temp_files: &mut Vec<PathBuf>
+) -> Result<(), Error> {
+    if image.animated {
+        return run_animation_pipeline(backend, image, out, options, progress, temp_files);
+    }
+
+    let input_path = match &options.crop {
+        Some(crop) => {
+            progress.stage(Stage::Preparing, "Cropping image...".to_string());
+
+            let cropped_path = crop.apply(&image.input_path)?;
+            temp_files.push(cropped_path.clone());
+
+            cropped_path
+        },
+        None => image.input_path.clone()
+    };
+
+    // upscayl-bin drops all metadata so we grab it from the source beforehand.
+    let metadata = match options.metadata {
+        MetadataPolicy::StripAll => None,
+        _ => match Metadata::read(&image.path) {
+            Ok(metadata) => Some(metadata),
+            Err(error) => {
+                log::warn!("Failed to read metadata from source image: {}", error);
+                None
+            }
+        }
+    };
+
+    let has_alpha = match (&options.alpha, &options.output_ext) {
+        (AlphaStrategy::Model, output_ext) if *output_ext != OutputExt::JPG => false,
+        _ => alpha::has_alpha(&input_path)
+    };
+
+    let split = match has_alpha && options.alpha != AlphaStrategy::Model {
+        true => {
+            progress.stage(Stage::Preparing, "Splitting alpha channel...".to_string());
+
+            let split = alpha::split(&input_path)?;
+            temp_files.extend([split.colour.clone(), split.alpha.clone()]);
+
+            Some(split)
+        },
+        false => None
+    };
+
+    // formats upscayl-bin can't write (or can't write with our settings) are 
+    // upscaled to a PNG first which we then post process and encode ourselves.
+    // tiles get stitched into a PNG so that one can be written straight to `out`.
+    let needs_encoding = split.is_some()
+        || (has_alpha && options.output_ext == OutputExt::JPG)
+        || match options.tiling.enabled {
+            true => options.output_ext != OutputExt::PNG,
+            false => !options.quality.backend_writes(&options.output_ext)
+        };
+
+    // stitching streams to disk but encoding anything else loads the whole upscaled image.
+    if options.tiling.enabled && needs_encoding {
+        if let Ok(size) = imagesize::size(&input_path) {
+            let scale = options.scale as u64;
+            let estimate = tiling::memory_estimate(size.width as u64 * scale, size.height as u64 * scale);
+
+            if estimate > tiling::LARGE_IMAGE_MEMORY {
+                progress.warning(
+                    Error::FailedToTileImage(
+                        None,
+                        format!(
+                            "Encoding the stitched image will take about {} GB of memory, save it as a PNG to avoid that.",
+                            estimate / (1024 * 1024 * 1024)
+                        )
+                    )
+                );
+            }
+        }
+    }
+
+    let upscaled_path = match needs_encoding {
+        true => {
+            let upscaled_path = temp_path(&image.path, "upscaled");
+            temp_files.push(upscaled_path.clone());
+
+            upscaled_path
+        },
+        false => out.to_path_buf()
+    };
+
+    let backend_input = match &split {
+        Some(split) => &split.colour,
+        None => &input_path
+    };
+
+    upscale_image(
+        backend,
+        image,
+        backend_input,
+        &upscaled_path,
+        options,
+        options.quality.backend_compression(&options.output_ext),
+        "Processing",
+        false,
+        progress
+    )?;
+
+    if let Some(split) = &split {
+        let upscaled_alpha = match options.alpha {
+            AlphaStrategy::SeparateModel => {
+                let upscaled_alpha = temp_path(&image.path, "upscaled_alpha");
+                temp_files.push(upscaled_alpha.clone());
+
+                upscale_image(
+                    backend, image, &split.alpha, &upscaled_alpha, options, 0, "Processing alpha", true, progress
+                )?;
+
+                upscaled_alpha
+            },
+            _ => split.alpha.clone()
+        };
+
+        progress.stage(Stage::PostProcessing, "Recombining alpha channel...".to_string());
+
+        alpha::recombine(&upscaled_path, &upscaled_alpha)?;
+    }
+
+    if needs_encoding {
+        progress.stage(Stage::PostProcessing, format!("Encoding {}...", options.output_ext));
+
+        encode::encode(&upscaled_path, out, &options.output_ext, &options.quality)?;
+    }
+
+    if let Some(metadata) = &metadata {
+        progress.stage(Stage::PostProcessing, "Copying metadata...".to_string());
+
+        let result = match imagesize::size(out) {
+            Ok(size) => metadata.write(
+                out, options.metadata, (size.width as u32, size.height as u32)
+            ),
+            Err(error) => Err(
+                Error::FailedToCopyMetadata(
+                    Some(error.to_string()),
+                    "Failed to get the upscaled image size.".to_string()
+                )
+            )
+        };
+
+        // the upscale itself went fine so this is only worth a warning.
+        if let Err(error) = result {
+            progress.warning(error);
+        }
+    }
+
+    Ok(())
+}
+
+/// Upscales `input` to `output` with upscayl-bin, either in one go or tile by tile
+/// when tiling is enabled (in which case `output` is always written as a PNG).
+/// `alpha_pass` is set when `input` is the alpha channel split off from the image.
+#[allow(clippy::too_many_arguments)]
+fn upscale_image(
+    backend: &Backend,
+    image: &Image,
+    input: &Path,
+    output: &Path,
+    options: &UpscaleOptions,
+    compression: u8,
+    stage: &str,
+    alpha_pass: bool,
+    progress: &dyn Progress
+) -> Result<(), Error> {
+    if !options.tiling.enabled {
+        return run_backend(backend, input, output, options, compression, stage, progress).map(|_| ());
+    }
+
+    let size = imagesize::size(input).map_err(
+        |error| Error::FailedToTileImage(Some(error.to_string()), "Failed to get the image size.".to_string())
+    )?;
+
+    let tiles = options.tiling.plan(size.width as u32, size.height as u32);
+
+    // a source that's been edited since shouldn't resume from the old one's tiles.
+    let source = fs::metadata(&image.path)
+        .map(|metadata| (metadata.len(), metadata.modified().ok()))
+        .ok();
+
+    // the alpha strategy decides whether the tiles are of the colour with or without alpha.
+    let folder = tiling::tile_folder(
+        (
+            &image.path,
+            source,
+            options.scale,
+            &options.model.as_ref().unwrap().name,
+            options.crop,
+            &options.tiling,
+            options.alpha,
+            alpha_pass
+        )
+    );
+
+    progress.stage(Stage::Preparing, format!("Splitting into {} tiles...", tiles.len()));
+
+    let tile_paths = tiling::split(input, &tiles, &folder)?;
+    let mut upscaled_tiles = Vec::with_capacity(tiles.len());
+
+    for (index, tile_path) in tile_paths.iter().enumerate() {
+        let upscaled_tile = folder.join(format!("upscaled_{}.png", index));
+
+        // tiles left over from a failed attempt at this same job get reused.
+        if !upscaled_tile.exists() {
+            let partial_tile = folder.join(format!("upscaled_{}_partial.png", index));
+
+            run_backend(
+                backend,
+                tile_path,
+                &partial_tile,
+                options,
+                0,
+                &format!("{} tile {}/{}", stage, index + 1, tiles.len()),
+                progress
+            ).map_err(
+                |error| Error::FailedToTileImage(
+                    Some(error.to_string()),
+                    format!(
+                        "Tile {} of {} failed. Upscale again with the same settings to resume from it.",
+                        index + 1,
+                        tiles.len()
+                    )
+                )
+            )?;
+
+            fs::rename(&partial_tile, &upscaled_tile).map_err(
+                |error| Error::FailedToTileImage(Some(error.to_string()), "Failed to store an upscaled tile.".to_string())
+            )?;
+        }
+
+        upscaled_tiles.push(upscaled_tile);
+    }
+
+    progress.stage(Stage::PostProcessing, "Stitching tiles...".to_string());
+
+    tiling::stitch(
+        &tiles, &upscaled_tiles, &options.tiling, options.scale as u32, options.quality.png_compression, output
+    )?;
+
+    let _ = fs::remove_dir_all(&folder);
+
+    Ok(())
+}
+
+/// Upscales every frame of an animated image and encodes them back into an animation.
+fn run_animation_pipeline(
+    backend: &Backend,
+    image: &Image,
+    out: &Path,
+    options: &UpscaleOptions,
+    progress: &dyn Progress,
+    temp_files: &mut Vec<PathBuf>
+) -> Result<(), Error> {
+    progress.stage(Stage::Preparing, "Extracting frames...".to_string());
+
+    let animation = animation::extract(&image.path)?;
+    temp_files.extend(animation.frames.iter().cloned());
+
+    let frame_count = animation.frames.len();
+    let mut upscaled_frames = Vec::with_capacity(frame_count);
+
+    for (index, frame_path) in animation.frames.iter().enumerate() {
+        let input_path = match &options.crop {
+            Some(crop) => {
+                let cropped_path = crop.apply(frame_path)?;
+                temp_files.push(cropped_path.clone());
+
+                cropped_path
+            },
+            None => frame_path.clone()
+        };
+
+        let upscaled_frame = temp_path(frame_path, "upscaled");
+        temp_files.push(upscaled_frame.clone());
+
+        run_backend(
+            backend,
+            &input_path,
+            &upscaled_frame,
+            options,
+            0,
+            &format!("Frame {}/{}", index + 1, frame_count),
+            progress
+        )?;
+
+        upscaled_frames.push(upscaled_frame);
+    }
+
+    let output_ext = animation::output_ext(&options.output_ext);
+
+    progress.stage(Stage::PostProcessing, format!("Encoding animated {}...", output_ext));
+
+    animation::assemble(&upscaled_frames, &animation, out, &output_ext, &options.quality)
+}
+
+/// What actually upscales the images.
+#[derive(Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+pub enum BackendKind {
+    #[default]
+    Upscayl,
+    /// Resamples the image instead, for testing without a GPU or any models.
+    Mock
+}
+
+/// One upscayl-bin worker, optionally pinned to a GPU and limited to a number of threads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backend {
+    pub slot: usize,
+    pub kind: BackendKind,
+    pub cli_path: PathBuf,
+    pub gpu_id: Option<i32>,
+    pub threads: Option<u32>,
+    /// The tile size upscayl-bin splits the image into, none lets it pick one.
+    pub tile_size: Option<u32>
+}
+
+impl Backend {
+    /// The upscayl-bin command that upscales `input` to `output` with `model`.
+    pub fn command(&self, input: &Path, output: &Path, model: &Model, scale: i8, compression: u8) -> Command {
+        let mut upscale_command = Command::new(self.cli_path.to_string_lossy().to_string());
+
+        #[cfg(target_os = "windows")] {
+            use std::os::windows::process::CommandExt;
+
+            upscale_command.creation_flags(0x08000000);
+        }
+
+        upscale_command.args([
+            "-i",
+            &input.to_string_lossy(),
+            "-o",
+            &output.to_string_lossy(),
+            "-m",
+            &model.folder.to_string_lossy(),
+            "-n",
+            &model.name,
+            "-s",
+            &scale.to_string(),
+            "-c",
+            &compression.to_string()
+        ]);
+
+        if let Some(gpu_id) = self.gpu_id {
+            upscale_command.args(["-g", &gpu_id.to_string()]);
+        }
+
+        // only the processing threads, loading and saving stay at one each.
+        if let Some(threads) = self.threads {
+            upscale_command.args(["-j", &format!("1:{}:1", threads)]);
+        }
+
+        if let Some(tile_size) = self.tile_size {
+            upscale_command.args(["-t", &tile_size.to_string()]);
+        }
+
+        upscale_command
+    }
+
+    /// Short description for showing which worker runs a job.
+    pub fn label(&self) -> String {
+        match self.gpu_id {
+            Some(gpu_id) => format!("worker {} (GPU {})", self.slot + 1, gpu_id),
+            None => format!("worker {}", self.slot + 1)
+        }
+    }
+}
+
+/// Runs upscayl-bin on a single image, reporting its progress as it goes. Returns the most
+/// memory upscayl-bin had used by the last line it printed, this is only measured on linux.
+pub fn run_backend(
+    backend: &Backend,
+    input: &Path,
+    output: &Path,
+    options: &UpscaleOptions,
+    compression: u8,
+    stage: &str,
+    progress: &dyn Progress
+) -> Result<Option<u64>, Error> {
+    // tiles and frames each get their own run so this is also where those stop.
+    if progress.cancelled() {
+        return Err(cancelled_error());
+    }
+
+    progress.stage(Stage::Running, "Initializing command...".to_string());
+
+    if backend.kind == BackendKind::Mock {
+        return mock::upscale(
+            input,
+            output,
+            &options.model.as_ref().unwrap().name,
+            options.scale,
+            &mut |line| report_progress(line, stage, progress)
+        ).map(|_| None);
+    }
+
+    let cmd = backend.command(
+        input, output, options.model.as_ref().unwrap(), options.scale, compression
+    )
+        .stderr(Stdio::piped()) // why do you output to stderr :woe: ~ Ananas
+        .spawn();
+
+    let mut child = match cmd {
+        Ok(child) => child,
+        Err(error) => return Err(
+            Error::FailedToUpscaleImage(Some(error.to_string()), "Failed to spawn child process.".to_string())
+        )
+    };
+
+    let mut peak_memory_bytes = None;
+
+    if let Some(stderr) = child.stderr.take() {
+        let reader = BufReader::new(stderr);
+
+        for line in reader.lines() {
+            // the kernel keeps track of the high water mark so the last reading is the peak.
+            peak_memory_bytes = read_peak_memory(child.id()).or(peak_memory_bytes);
+
+            if progress.cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+
+                return Err(cancelled_error());
+            }
+
+            match line {
+                Ok(output) => report_progress(&output, stage, progress),
+                _ => {}
+            }
+        }
+    }
+
+    match child.wait_with_output() {
+        Ok(status) => {
+            if status.status.success() {
+                Ok(peak_memory_bytes)
+            } else {
+                Err(
+                    Error::FailedToUpscaleImage(
+                        None,
+                        "Process returned as not successful.".to_string()
+                    )
+                )
+            }
+        },
+        Err(error) => Err(
+            Error::FailedToUpscaleImage(
+                Some(error.to_string()),
+                "Failed to wait for process.".to_string()
+            )
+        )
+    }
+}
+
+/// The peak resident memory of the process, read from procfs so none outside of linux.
+fn read_peak_memory(pid: u32) -> Option<u64> {
+    let status = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+
+    let kilobytes = status.lines()
+        .find(|line| line.starts_with("VmHWM:"))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+/// Passes upscayl-bin's percentage lines on as progress, ignoring everything else it prints.
+fn report_progress(line: &str, stage: &str, progress: &dyn Progress) {
+    let out_bytes = line.as_bytes();
+
+    if !out_bytes.is_empty() && out_bytes[0].is_ascii_digit() {
+        progress.stage(Stage::Running, format!("{}: {}", stage, line));
+    }
+}
@@ -1,9 +1,6 @@
-use cirrus_egui::v1::{notifier::Notifier, widgets::about::{authors_toml_to_about_authors, About, AboutApplicationInfo}};
-use eframe::egui::{self, Key, Response, Vec2};
+use cirrus_egui::v1::{widgets::about::{authors_toml_to_about_authors, About, AboutApplicationInfo}};
+use eframe::egui::{self, Response, Vec2};
 use egui::include_image;
-use egui_notify::ToastLevel;
-
-use crate::{config::config::Config};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const AUTHORS: &str = include_str!("../../authors.toml");
@@ -11,25 +8,11 @@ const AUTHORS: &str = include_str!("../../authors.toml");
 pub struct AboutWindow<'a> {
     pub show: bool,
     about_widget: About<'a>,
-    toggle_key: Key,
     pub response: Option<Response>,
 }
 
 impl<'a> AboutWindow<'a> {
-    pub fn new(config: &Config, notifier: &Notifier) -> Self {        
-        let config_key = match Key::from_name(&config.keybinds.about_box) {
-            Some(key) => key,
-            None => {
-                notifier.toast(
-                    "The key bind set for 'about_box' is invalid! Defaulting to `A`.", 
-                    ToastLevel::Error,
-                    |_| {}
-                );
-
-                Key::A
-            },
-        };
-
+    pub fn new() -> Self {
         let about_app_info = AboutApplicationInfo {
             name: "Aeternum".to_string(),
             description: "A simple and minimal upscaler built in rust".to_string(),
@@ -48,21 +31,10 @@ impl<'a> AboutWindow<'a> {
         Self {
             show: false,
             about_widget,
-            toggle_key: config_key,
             response: None
         }
     }
 
-    pub fn handle_input(&mut self, ctx: &egui::Context) {
-        if ctx.input(|i| i.key_pressed(self.toggle_key)) {
-            if self.show == true {
-                self.show = false;
-            } else {
-                self.show = true;
-            }
-        }
-    }
-
     pub fn update(&mut self, ctx: &egui::Context) {
         if self.show {
             let default_window_size = Vec2::new(340.0, 350.0);
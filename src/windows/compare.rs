@@ -0,0 +1,220 @@
+use std::{path::PathBuf, sync::{Arc, Mutex}};
+
+use cirrus_egui::v1::notifier::Notifier;
+use eframe::egui::{self, Rect, RichText, Vec2};
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use crate::{compare::{self, Comparison, ModelScore}, image::Image, upscale::Upscale};
+
+/// The part of each image shown in the crop grid, in uv coordinates.
+const CROP_UV: Rect = Rect {
+    min: egui::Pos2 { x: 0.375, y: 0.375 },
+    max: egui::Pos2 { x: 0.625, y: 0.625 }
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumIter, Display)]
+pub enum RankBy {
+    #[strum(to_string = "SSIM")]
+    Ssim,
+    #[strum(to_string = "PSNR")]
+    Psnr,
+    #[strum(to_string = "Sharpness")]
+    Sharpness
+}
+
+pub struct CompareWindow {
+    pub show: bool,
+    selected_models: Vec<String>,
+    rank_by: RankBy,
+    comparison: Arc<Mutex<Comparison>>,
+    /// The job running the last comparison.
+    job: Option<usize>
+}
+
+impl CompareWindow {
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            selected_models: Vec::new(),
+            rank_by: RankBy::Ssim,
+            comparison: Arc::new(Mutex::new(Comparison::default())),
+            job: None
+        }
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context, upscale: &mut Upscale, image: Option<&Image>, notifier: &Notifier) {
+        if !self.show {
+            return;
+        }
+
+        let default_window_size = Vec2::new(560.0, 480.0);
+
+        let mut show = self.show;
+
+        egui::Window::new(
+            egui::WidgetText::RichText(
+                egui::RichText::new("⚖ Compare Models").size(15.0).into()
+            )
+        )
+            .open(&mut show)
+            .default_size(default_window_size)
+            .default_pos(ctx.screen_rect().center() - default_window_size / 2.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.show_setup(ui, upscale, image, notifier);
+
+                    ui.separator();
+
+                    let comparison = match self.comparison.try_lock() {
+                        Ok(comparison) => comparison,
+                        Err(_) => return
+                    };
+
+                    if comparison.scores.is_empty() && comparison.failed.is_empty() {
+                        ui.label(
+                            "Each model upscales a downscaled copy of the image (or crop) \
+                            and gets scored on how close it gets to the original."
+                        );
+                        return;
+                    }
+
+                    let mut scores = comparison.scores.clone();
+                    let rank_by = self.rank_by;
+
+                    scores.sort_by(|a, b| metric(b, rank_by).total_cmp(&metric(a, rank_by)));
+
+                    show_table(ui, &scores, &comparison.failed);
+
+                    // every model failed, there's nothing to crop.
+                    if scores.is_empty() {
+                        return;
+                    }
+
+                    ui.add_space(10.0);
+                    ui.label(RichText::new("Centre crops").strong());
+
+                    show_crop_grid(ui, comparison.ground_truth.as_ref(), &scores);
+                });
+            });
+
+        self.show = show;
+    }
+
+    fn show_setup(&mut self, ui: &mut egui::Ui, upscale: &mut Upscale, image: Option<&Image>, notifier: &Notifier) {
+        let running = self.job
+            .and_then(|id| upscale.job(id))
+            .is_some_and(|job| !job.state.finished());
+
+        ui.label(RichText::new("Models").strong());
+
+        ui.horizontal_wrapped(|ui| {
+            for model in upscale.models.iter() {
+                let mut selected = self.selected_models.contains(&model.name);
+
+                if ui.checkbox(&mut selected, &model.name).changed() {
+                    match selected {
+                        true => self.selected_models.push(model.name.clone()),
+                        false => self.selected_models.retain(|name| *name != model.name)
+                    }
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("rank by")
+                .selected_text(self.rank_by.to_string())
+                .show_ui(ui, |ui| {
+                    for rank_by in RankBy::iter() {
+                        ui.selectable_value(&mut self.rank_by, rank_by, rank_by.to_string());
+                    }
+                });
+
+            // it's queued like an upscale so it doesn't matter if any are still running.
+            let can_run = !running && image.is_some() && !self.selected_models.is_empty();
+
+            let run_button = ui.add_enabled(can_run, egui::Button::new("Run"))
+                .on_disabled_hover_text("Open an image, select some models and wait for the last comparison to finish.");
+
+            if let (true, Some(image)) = (run_button.clicked(), image) {
+                let models = upscale.models.iter()
+                    .filter(|model| self.selected_models.contains(&model.name))
+                    .cloned()
+                    .collect();
+
+                self.job = Some(compare::run(
+                    upscale,
+                    models,
+                    image,
+                    upscale.options.crop,
+                    upscale.options.scale,
+                    notifier,
+                    self.comparison.clone()
+                ));
+            }
+        });
+    }
+}
+
+fn metric(score: &ModelScore, rank_by: RankBy) -> f64 {
+    match rank_by {
+        RankBy::Ssim => score.ssim,
+        RankBy::Psnr => score.psnr,
+        RankBy::Sharpness => score.sharpness
+    }
+}
+
+fn show_table(ui: &mut egui::Ui, scores: &[ModelScore], failed: &[(String, String)]) {
+    egui::Grid::new("compare_table")
+        .striped(true)
+        .spacing([15.0, 4.0])
+        .show(ui, |ui| {
+            for header in ["#", "Model", "SSIM", "PSNR", "Sharpness", "Time"] {
+                ui.label(RichText::new(header).strong());
+            }
+            ui.end_row();
+
+            for (rank, score) in scores.iter().enumerate() {
+                ui.label(format!("{}", rank + 1));
+                ui.label(&score.model);
+                ui.label(format!("{:.4}", score.ssim));
+                ui.label(format!("{:.2} dB", score.psnr));
+                ui.label(format!("{:.1}", score.sharpness));
+                ui.label(format!("{:.1}s", score.seconds));
+                ui.end_row();
+            }
+
+            for (model, reason) in failed {
+                ui.label("-");
+                ui.label(model).on_hover_text(reason);
+                ui.label("failed");
+                ui.end_row();
+            }
+        });
+}
+
+fn show_crop_grid(ui: &mut egui::Ui, ground_truth: Option<&PathBuf>, scores: &[ModelScore]) {
+    let size = Vec2::splat(150.0);
+
+    let mut tiles = Vec::new();
+
+    if let Some(path) = ground_truth {
+        tiles.push(("Ground truth".to_string(), path));
+    }
+
+    tiles.extend(scores.iter().map(|score| (score.model.clone(), &score.output)));
+
+    ui.horizontal_wrapped(|ui| {
+        for (name, path) in tiles {
+            ui.vertical(|ui| {
+                ui.add(
+                    egui::Image::from_uri(format!("file://{}", path.to_string_lossy()))
+                        .uv(CROP_UV)
+                        .fit_to_exact_size(size)
+                        .corner_radius(4.0)
+                );
+                ui.label(RichText::new(name).size(10.0));
+            });
+        }
+    });
+}
@@ -0,0 +1,176 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use cirrus_egui::v1::notifier::Notifier;
+use eframe::egui::{self, Color32, RichText, Vec2};
+use egui_notify::ToastLevel;
+
+use crate::{files, history::{self, History, HistoryEntry, JobStatus}};
+
+pub struct HistoryWindow {
+    pub show: bool,
+    /// The entries as of the last time we got the lock, with the revision they're from.
+    snapshot: Option<(u64, Vec<ShownEntry>)>
+}
+
+/// An entry along with whether its files were there when the snapshot was taken,
+/// checking on every frame would be a couple of stats per entry per repaint.
+struct ShownEntry {
+    entry: HistoryEntry,
+    input_exists: bool,
+    output_exists: bool
+}
+
+impl ShownEntry {
+    fn new(entry: &HistoryEntry) -> Self {
+        Self {
+            entry: entry.clone(),
+            input_exists: entry.input.exists(),
+            output_exists: entry.output.exists()
+        }
+    }
+}
+
+impl HistoryWindow {
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            snapshot: None
+        }
+    }
+
+    /// Shows the history window, returning the entry the user wants to re-run if any.
+    pub fn update(&mut self, ctx: &egui::Context, history: &Arc<Mutex<History>>, notifier: &Notifier) -> Option<HistoryEntry> {
+        // taken again once it's reopened so files deleted in the meantime show as gone.
+        if !self.show {
+            self.snapshot = None;
+            return None;
+        }
+
+        let mut rerun = None;
+        let default_window_size = Vec2::new(480.0, 400.0);
+
+        // the upscale thread holds the lock while saving so we keep showing the last entries we saw.
+        if let Ok(history) = history.try_lock() {
+            let up_to_date = matches!(&self.snapshot, Some((revision, _)) if *revision == history.revision);
+
+            if !up_to_date {
+                self.snapshot = Some((history.revision, history.entries.iter().map(ShownEntry::new).collect()));
+            }
+        }
+
+        let entries = match &self.snapshot {
+            Some((_, entries)) => entries,
+            None => return None
+        };
+
+        let mut clear = false;
+
+        egui::Window::new(
+            egui::WidgetText::RichText(
+                egui::RichText::new("🕒 History").size(15.0).into()
+            )
+        )
+            .open(&mut self.show)
+            .default_size(default_window_size)
+            .default_pos(ctx.screen_rect().center() - default_window_size / 2.0)
+            .show(ctx, |ui| {
+                if entries.is_empty() {
+                    ui.label("Nothing has been upscaled yet.");
+                    return;
+                }
+
+                if ui.button("Clear").clicked() {
+                    clear = true;
+                    return;
+                }
+
+                ui.separator();
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let now = history::unix_timestamp();
+
+                    // newest first.
+                    for (index, shown) in entries.iter().enumerate().rev() {
+                        let entry = &shown.entry;
+
+                        ui.push_id(index, |ui| {
+                            let file_name = entry.input.file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy();
+
+                            let (status, colour) = match &entry.status {
+                                JobStatus::Succeeded => ("Succeeded".to_string(), Color32::LIGHT_GREEN),
+                                JobStatus::Failed(reason) => (format!("Failed: {}", reason), Color32::LIGHT_RED)
+                            };
+
+                            ui.label(RichText::new(file_name).strong());
+                            ui.label(
+                                RichText::new(
+                                    format!(
+                                        "{} ago, took {:.1} seconds (x{}, {}, {})",
+                                        format_age(now.saturating_sub(entry.finished_at)),
+                                        entry.duration_secs,
+                                        entry.options.scale,
+                                        entry.options.model.as_ref().map_or("no model", |model| &model.name),
+                                        entry.options.output_ext
+                                    )
+                                ).size(10.0)
+                            ).on_hover_text(format!("upscayl-bin: {}", entry.backend_version));
+                            ui.label(RichText::new(status).size(10.0).color(colour));
+
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(shown.output_exists, egui::Button::new("Open output")).clicked() {
+                                    toast_if_error(files::open_path(&entry.output), notifier);
+                                }
+
+                                if ui.add_enabled(shown.output_exists, egui::Button::new("Reveal in folder")).clicked() {
+                                    toast_if_error(files::reveal_in_folder(&entry.output), notifier);
+                                }
+
+                                let rerun_button = ui.add_enabled(
+                                    shown.input_exists, egui::Button::new("Re-run")
+                                ).on_hover_text("Upscale the input again with the same settings.");
+
+                                if rerun_button.clicked() {
+                                    rerun = Some(entry.clone());
+                                }
+                            });
+
+                            ui.separator();
+                        });
+                    }
+                });
+            });
+
+        if clear {
+            if let Ok(mut history) = history.lock() {
+                history.clear();
+            }
+
+            self.snapshot = None;
+        }
+
+        rerun
+    }
+}
+
+fn toast_if_error(result: Result<(), crate::Error>, notifier: &Notifier) {
+    if let Err(error) = result {
+        notifier.toast(
+            Box::new(error),
+            ToastLevel::Error,
+            |toast| {
+                toast.duration(Some(Duration::from_secs(5)));
+            }
+        );
+    }
+}
+
+fn format_age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{} seconds", seconds),
+        60..=3599 => format!("{} minutes", seconds / 60),
+        3600..=86399 => format!("{} hours", seconds / 3600),
+        _ => format!("{} days", seconds / 86400)
+    }
+}
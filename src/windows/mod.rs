@@ -1 +1,6 @@
-pub mod about;
\ No newline at end of file
+pub mod about;
+pub mod compare;
+pub mod history;
+pub mod plan;
+pub mod session;
+pub mod settings;
\ No newline at end of file
@@ -0,0 +1,71 @@
+use eframe::egui::{self, Color32, RichText, Vec2};
+
+use crate::plan::PlannedJob;
+
+/// Shows what an upscale would do before it's ran.
+pub struct PlanWindow {
+    pub show: bool,
+    jobs: Vec<PlannedJob>
+}
+
+impl PlanWindow {
+    pub fn new() -> Self {
+        Self {
+            show: false,
+            jobs: Vec::new()
+        }
+    }
+
+    pub fn open(&mut self, jobs: Vec<PlannedJob>) {
+        self.jobs = jobs;
+        self.show = true;
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context) {
+        if !self.show {
+            return;
+        }
+
+        let default_window_size = Vec2::new(420.0, 200.0);
+
+        egui::Window::new(
+            egui::WidgetText::RichText(
+                egui::RichText::new("📋 Plan").size(15.0).into()
+            )
+        )
+            .open(&mut self.show)
+            .default_size(default_window_size)
+            .default_pos(ctx.screen_rect().center() - default_window_size / 2.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for job in self.jobs.iter() {
+                        ui.label(RichText::new(job.input.to_string_lossy()).strong());
+
+                        if let Some(output) = &job.output {
+                            ui.label(format!("→ {}", output.to_string_lossy()));
+                        }
+
+                        ui.label(
+                            RichText::new(
+                                format!("{}, x{}", job.model.as_deref().unwrap_or("no model"), job.scale)
+                            ).size(10.0)
+                        );
+
+                        for error in job.errors.iter() {
+                            ui.label(RichText::new(error).size(10.0).color(Color32::LIGHT_RED));
+                        }
+
+                        for warning in job.warnings.iter() {
+                            ui.label(RichText::new(warning).size(10.0).color(Color32::from_rgb(230, 160, 40)));
+                        }
+
+                        if job.runnable() && job.warnings.is_empty() {
+                            ui.label(RichText::new("Ready to upscale.").size(10.0).color(Color32::LIGHT_GREEN));
+                        }
+
+                        ui.separator();
+                    }
+                });
+            });
+    }
+}
@@ -0,0 +1,80 @@
+use eframe::egui::{self, RichText, Vec2};
+
+use crate::session::SessionItem;
+
+pub enum SessionAction {
+    Resume,
+    /// Holds the ids of the items to throw away.
+    Discard(Vec<u64>)
+}
+
+/// Asks whether to pick up a batch the last run didn't get to finish.
+pub struct SessionWindow {
+    pub show: bool,
+    items: Vec<SessionItem>
+}
+
+impl SessionWindow {
+    pub fn new(items: Vec<SessionItem>) -> Self {
+        Self {
+            show: !items.is_empty(),
+            items
+        }
+    }
+
+    pub fn update(&mut self, ctx: &egui::Context) -> Option<SessionAction> {
+        if !self.show {
+            return None;
+        }
+
+        let default_window_size = Vec2::new(380.0, 180.0);
+
+        let mut action = None;
+
+        egui::Window::new(
+            egui::WidgetText::RichText(
+                egui::RichText::new("⟲ Resume Batch").size(15.0).into()
+            )
+        )
+            .collapsible(false)
+            .default_size(default_window_size)
+            .default_pos(ctx.screen_rect().center() - default_window_size / 2.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    format!(
+                        "Aeternum closed before finishing the last batch, {} image(s) were left. \
+                        Images that were already upscaled get skipped.",
+                        self.items.len()
+                    )
+                );
+
+                ui.add_space(5.0);
+
+                egui::ScrollArea::vertical().max_height(100.0).show(ui, |ui| {
+                    for item in self.items.iter() {
+                        ui.label(RichText::new(item.input.to_string_lossy()).size(10.0));
+                    }
+                });
+
+                ui.add_space(5.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("Resume").clicked() {
+                        action = Some(SessionAction::Resume);
+                    }
+
+                    if ui.button("Discard").clicked() {
+                        action = Some(
+                            SessionAction::Discard(self.items.iter().map(|item| item.id).collect())
+                        );
+                    }
+                });
+            });
+
+        if action.is_some() {
+            self.show = false;
+        }
+
+        action
+    }
+}
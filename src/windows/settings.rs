@@ -0,0 +1,378 @@
+use std::{collections::HashMap, path::Path};
+
+use eframe::egui::{self, Align, Color32, DragValue, Layout, RichText, Slider, Ui};
+use strum::IntoEnumIterator;
+
+use crate::{config::{config::Config, layers::ConfigLayers, structs::{backend::BackendPaths, defaults::Defaults, key_binds::KeyBinds, limits::Limits, misc::Misc, models::Models, theme::ThemeConfig, workers::Workers}}, files, processing::metadata::MetadataPolicy, shortcuts::Shortcut, theme, upscale::{Model, OutputExt}};
+
+/// Every option in the config grouped into sections. Text fields are only
+/// written to the config once what's typed into them is valid.
+pub struct SettingsPanel {
+    /// What's typed into each text field, by the field's id.
+    inputs: HashMap<String, String>
+}
+
+impl SettingsPanel {
+    pub fn new() -> Self {
+        Self {
+            inputs: HashMap::new()
+        }
+    }
+
+    /// Shows the settings, returning whether anything in the config changed.
+    pub fn update(&mut self, ui: &mut Ui, config: &mut Config, models: &[Model], config_layers: &ConfigLayers) -> bool {
+        let mut changed = false;
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.label(RichText::new("Settings").size(25.0).strong());
+
+            for layer in config_layers.layers() {
+                ui.label(
+                    RichText::new(format!("Options set in the {} win over these.", layer.name())).size(12.0)
+                );
+            }
+
+            changed |= self.key_binds_section(ui, config);
+            changed |= self.backend_section(ui, config);
+            changed |= self.defaults_section(ui, config, models);
+            changed |= self.output_section(ui, config);
+            changed |= self.theme_section(ui, config);
+        });
+
+        changed
+    }
+
+    fn key_binds_section(&mut self, ui: &mut Ui, config: &mut Config) -> bool {
+        let mut changed = false;
+
+        let reset = section(ui, "Key Binds", "A key with any of \"Ctrl\", \"Shift\" or \"Alt\" in front, like \"Ctrl+Shift+O\".", |ui| {
+            for shortcut in Shortcut::iter() {
+                let key_binds = config.keybinds.clone();
+
+                changed |= field(ui, &field_name(shortcut.config_key()), None, |ui| {
+                    self.text_field(
+                        ui,
+                        &format!("keybinds.{}", shortcut.config_key()),
+                        shortcut.key_bind_mut(&mut config.keybinds),
+                        |key_bind| key_bind.clone(),
+                        |string| shortcut.validate(string, &key_binds).map(|_| string.to_string())
+                    )
+                });
+            }
+        });
+
+        if reset {
+            config.keybinds = KeyBinds::default();
+            self.forget_inputs("keybinds.");
+        }
+
+        changed || reset
+    }
+
+    fn backend_section(&mut self, ui: &mut Ui, config: &mut Config) -> bool {
+        let mut changed = false;
+
+        let reset = section(ui, "Backend", "Where upscayl-bin is and how many of it runs at once.", |ui| {
+            changed |= field(ui, "upscayl-bin", Some("Leave empty to use the one that comes with Aeternum. Applies after a restart."), |ui| {
+                self.text_field(ui, "backend.upscayl_bin", &mut config.backend.upscayl_bin, |path| path.clone(), |string| {
+                    match string.is_empty() || Path::new(string).is_file() {
+                        true => Ok(string.to_string()),
+                        false => Err(format!("There's no file at '{}'.", string))
+                    }
+                })
+            });
+
+            changed |= field(ui, "Models folder", Some("Leave empty to use the models upscayl-bin comes with. Applies after a restart."), |ui| {
+                self.text_field(ui, "backend.models_folder", &mut config.backend.models_folder, |path| path.clone(), folder_or_empty)
+            });
+
+            changed |= field(ui, "Custom models", Some("Also load models from the \"models\" folder in Aeternum's config folder. Applies after a restart."), |ui| {
+                ui.checkbox(&mut config.models.custom_folder, "Enabled").changed()
+            });
+
+            changed |= field(ui, "Workers", Some("How many images get upscaled at the same time."), |ui| {
+                ui.add(DragValue::new(&mut config.workers.concurrency).range(1..=16)).changed()
+            });
+
+            changed |= field(ui, "GPU ids", Some("GPUs to spread the workers over separated by commas, leave empty to let upscayl-bin pick."), |ui| {
+                self.text_field(
+                    ui,
+                    "backend.gpu_ids",
+                    &mut config.workers.gpu_ids,
+                    |gpu_ids| gpu_ids.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(", "),
+                    |string| string.split(',')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(|id| id.parse::<i32>().map_err(|_| format!("'{}' isn't a GPU id.", id)))
+                        .collect()
+                )
+            });
+
+            changed |= field(ui, "CPU threads", Some("Threads shared out between the workers, 0 lets upscayl-bin decide."), |ui| {
+                ui.add(DragValue::new(&mut config.workers.cpu_threads).range(0..=256)).changed()
+            });
+        });
+
+        if reset {
+            config.backend = BackendPaths::default();
+            config.workers = Workers::default();
+            config.models = Models::default();
+            self.forget_inputs("backend.");
+        }
+
+        changed || reset
+    }
+
+    fn defaults_section(&mut self, ui: &mut Ui, config: &mut Config, models: &[Model]) -> bool {
+        let mut changed = false;
+
+        let reset = section(ui, "Defaults", "What every new image starts out with.", |ui| {
+            let defaults = &mut config.defaults;
+
+            changed |= field(ui, "Model", None, |ui| {
+                let mut changed = false;
+
+                let selected = match defaults.model.is_empty() {
+                    true => "None".to_string(),
+                    false => defaults.model.clone()
+                };
+
+                egui::ComboBox::from_id_salt("settings_default_model")
+                    .selected_text(selected)
+                    .width(230.0)
+                    .show_ui(ui, |ui| {
+                        changed |= ui.selectable_value(&mut defaults.model, String::new(), "None").changed();
+
+                        for model in models.iter() {
+                            changed |= ui.selectable_value(&mut defaults.model, model.name.clone(), &model.name).changed();
+                        }
+                    });
+
+                if !defaults.model.is_empty() && !models.iter().any(|model| model.name == defaults.model) {
+                    error_label(ui, &format!("There's no model named '{}'.", defaults.model));
+                }
+
+                changed
+            });
+
+            changed |= field(ui, "Scale", None, |ui| {
+                ui.add(Slider::new(&mut defaults.scale, 1..=16)).changed()
+            });
+
+            changed |= field(ui, "Format", None, |ui| {
+                let mut changed = false;
+
+                egui::ComboBox::from_id_salt("settings_default_format")
+                    .selected_text(&defaults.format)
+                    .width(230.0)
+                    .show_ui(ui, |ui| {
+                        for extension in OutputExt::iter() {
+                            changed |= ui.selectable_value(&mut defaults.format, extension.to_string(), extension.to_string()).changed();
+                        }
+                    });
+
+                if defaults.format.parse::<OutputExt>().is_err() {
+                    error_label(ui, &format!("Can't save as '{}'.", defaults.format));
+                }
+
+                changed
+            });
+
+            changed |= field(ui, "Metadata", Some("What EXIF, XMP and colour profile data from the image is copied into the upscale."), |ui| {
+                let mut changed = false;
+
+                egui::ComboBox::from_id_salt("settings_default_metadata")
+                    .selected_text(&defaults.metadata)
+                    .width(230.0)
+                    .show_ui(ui, |ui| {
+                        for policy in MetadataPolicy::iter() {
+                            changed |= ui.selectable_value(&mut defaults.metadata, policy.to_string(), policy.to_string()).changed();
+                        }
+                    });
+
+                if defaults.metadata.parse::<MetadataPolicy>().is_err() {
+                    error_label(ui, &format!("'{}' isn't a metadata policy.", defaults.metadata));
+                }
+
+                changed
+            });
+        });
+
+        if reset {
+            let defaults = Defaults::default();
+
+            config.defaults.model = defaults.model;
+            config.defaults.scale = defaults.scale;
+            config.defaults.format = defaults.format;
+            config.defaults.metadata = defaults.metadata;
+        }
+
+        changed || reset
+    }
+
+    fn output_section(&mut self, ui: &mut Ui, config: &mut Config) -> bool {
+        let mut changed = false;
+
+        let reset = section(ui, "Output", "Where upscales go and what we warn about before upscaling.", |ui| {
+            changed |= field(ui, "Output folder", Some("Leave empty to save upscales next to the image."), |ui| {
+                let mut changed = false;
+
+                ui.horizontal(|ui| {
+                    changed |= self.text_field(ui, "output.folder", &mut config.defaults.output_folder, |path| path.clone(), folder_or_empty);
+
+                    if ui.button("Browse").clicked() {
+                        if let Ok(folder) = files::save_folder() {
+                            config.defaults.output_folder = folder.to_string_lossy().to_string();
+                            self.forget_inputs("output.folder");
+                            changed = true;
+                        }
+                    }
+                });
+
+                changed
+            });
+
+            changed |= field(ui, "Preview output", Some("Switch the preview to the upscaled image once upscaling finishes."), |ui| {
+                ui.checkbox(&mut config.misc.auto_preview_output, "Enabled").changed()
+            });
+
+            let limits = &mut config.limits;
+
+            changed |= field(ui, "Max output size", Some("Warn when the output goes past this many megapixels, 0 turns it off."), |ui| {
+                ui.add(DragValue::new(&mut limits.max_output_megapixels).suffix(" MP")).changed()
+            });
+
+            changed |= field(ui, "Max file size", Some("Warn when the output file would go past this, 0 turns it off."), |ui| {
+                ui.add(DragValue::new(&mut limits.max_file_size_mb).suffix(" MB")).changed()
+            });
+
+            changed |= field(ui, "Max duration", Some("Warn when upscaling would take longer than this, 0 turns it off."), |ui| {
+                ui.add(DragValue::new(&mut limits.max_duration_minutes).suffix(" min")).changed()
+            });
+        });
+
+        if reset {
+            config.defaults.output_folder = Defaults::default().output_folder;
+            config.misc.auto_preview_output = Misc::default().auto_preview_output;
+            config.limits = Limits::default();
+            self.forget_inputs("output.");
+        }
+
+        changed || reset
+    }
+
+    fn theme_section(&mut self, ui: &mut Ui, config: &mut Config) -> bool {
+        let mut changed = false;
+
+        let reset = section(ui, "Theme", "How Aeternum looks, changes here apply after a restart.", |ui| {
+            changed |= field(ui, "Theme", Some("\"system\" follows your OS, \"dark\", \"light\" or the name of a file in the \"themes\" config folder."), |ui| {
+                self.text_field(ui, "theme.name", &mut config.theme.name, |name| name.clone(), |string| {
+                    theme::validate_name(string).map(|_| string.to_string())
+                })
+            });
+
+            changed |= field(ui, "Accent colour", Some("A hex code like \"#e05f78\", leave empty to use the theme's own."), |ui| {
+                self.text_field(ui, "theme.accent_colour", &mut config.theme.accent_colour, |colour| colour.clone(), |string| {
+                    match string.is_empty() || theme::parse_colour(string).is_some() {
+                        true => Ok(string.to_string()),
+                        false => Err(format!("'{}' isn't a hex code.", string))
+                    }
+                })
+            });
+        });
+
+        if reset {
+            config.theme = ThemeConfig::default();
+            self.forget_inputs("theme.");
+        }
+
+        changed || reset
+    }
+
+    /// A text field for `value`, only writing to it when `parse` accepts what's typed
+    /// and showing why it didn't otherwise. Returns whether `value` changed.
+    fn text_field<T: PartialEq>(
+        &mut self,
+        ui: &mut Ui,
+        id: &str,
+        value: &mut T,
+        to_string: impl Fn(&T) -> String,
+        parse: impl Fn(&str) -> Result<T, String>
+    ) -> bool {
+        let input = self.inputs.entry(id.to_string()).or_insert_with(|| to_string(value));
+
+        ui.add(egui::TextEdit::singleline(input).desired_width(230.0));
+
+        match parse(input.trim()) {
+            Ok(parsed) if parsed != *value => {
+                *value = parsed;
+                true
+            },
+            Ok(_) => false,
+            Err(error) => {
+                error_label(ui, &error);
+                false
+            }
+        }
+    }
+
+    /// Drops what was typed into fields starting with `prefix` so they show the config's value again.
+    fn forget_inputs(&mut self, prefix: &str) {
+        self.inputs.retain(|id, _| !id.starts_with(prefix));
+    }
+}
+
+/// A titled group of fields, returning whether its "Reset to defaults" button was clicked.
+fn section(ui: &mut Ui, title: &str, description: &str, add_contents: impl FnOnce(&mut Ui)) -> bool {
+    let mut reset = false;
+
+    ui.add_space(15.0);
+
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(title).size(20.0).strong());
+
+        ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+            reset = ui.button("Reset to defaults").clicked();
+        });
+    });
+
+    ui.label(RichText::new(description).size(10.0));
+    ui.separator();
+
+    add_contents(ui);
+
+    reset
+}
+
+fn field(ui: &mut Ui, name: &str, description: Option<&str>, add_editor: impl FnOnce(&mut Ui) -> bool) -> bool {
+    ui.add_space(5.0);
+    ui.label(RichText::new(name).size(14.0).strong());
+
+    if let Some(description) = description {
+        ui.label(RichText::new(description).size(10.0));
+    }
+
+    add_editor(ui)
+}
+
+fn error_label(ui: &mut Ui, error: &str) {
+    ui.label(RichText::new(error).size(10.0).color(Color32::LIGHT_RED));
+}
+
+fn folder_or_empty(string: &str) -> Result<String, String> {
+    match string.is_empty() || Path::new(string).is_dir() {
+        true => Ok(string.to_string()),
+        false => Err(format!("There's no folder at '{}'.", string))
+    }
+}
+
+/// Turns a config key like "open_output_folder" into "Open output folder".
+fn field_name(key: &str) -> String {
+    let name = key.replace('_', " ");
+    let mut characters = name.chars();
+
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => name
+    }
+}
\ No newline at end of file
@@ -0,0 +1,313 @@
+//! Runs headless upscales against the mock backend so the whole pipeline
+//! gets exercised without upscayl-bin, a GPU or any models installed.
+
+use std::{fs, io::Cursor, path::{Path, PathBuf}, process::{Command, Output}};
+
+use exif::{experimental::Writer, Field, In, Tag};
+use img_parts::{jpeg::Jpeg, ImageEXIF};
+use serde_json::Value;
+
+#[path = "../src/test_utils.rs"]
+mod test_utils;
+
+use test_utils::{write_image, TestFolder};
+
+/// Runs aeternum headless on the mock backend, keeping its config away from the user's.
+fn aeternum(folder: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_aeternum"))
+        .args(["--headless", "--backend", "mock"])
+        .args(args)
+        .env("XDG_CONFIG_HOME", folder.join("config"))
+        .output()
+        .unwrap()
+}
+
+fn events(output: &Output) -> Vec<Value> {
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+/// Writes a `width` x `height` JPEG whose EXIF says to rotate it 90° clockwise to show it upright.
+fn write_rotated_jpeg(path: &Path, width: u32, height: u32) {
+    write_image(path, width, height);
+
+    let field = Field {
+        tag: Tag::Orientation,
+        ifd_num: In::PRIMARY,
+        value: exif::Value::Short(vec![6])
+    };
+
+    let mut writer = Writer::new();
+    let mut exif = Cursor::new(Vec::new());
+
+    writer.push_field(&field);
+    writer.write(&mut exif, false).unwrap();
+
+    let mut jpeg = Jpeg::from_bytes(fs::read(path).unwrap().into()).unwrap();
+
+    jpeg.set_exif(Some(exif.into_inner().into()));
+    fs::write(path, jpeg.encoder().bytes()).unwrap();
+}
+
+fn events_named<'a>(events: &'a [Value], name: &str) -> Vec<&'a Value> {
+    events.iter()
+        .filter(|event| event["event"] == name)
+        .collect()
+}
+
+#[test]
+fn upscales_an_image() {
+    let folder = TestFolder::new("upscales_an_image");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 16, 12);
+
+    let output = aeternum(&folder, &["--json", "-m", "mock-x4", "-s", "4", input.to_str().unwrap()]);
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(0));
+
+    let finished = events_named(&events, "finished");
+    assert_eq!(finished.len(), 1);
+
+    let upscaled = PathBuf::from(finished[0]["output"].as_str().unwrap());
+    let size = imagesize::size(&upscaled).unwrap();
+
+    assert_eq!((size.width, size.height), (64, 48));
+}
+
+#[test]
+fn reports_every_job_state() {
+    let folder = TestFolder::new("reports_every_job_state");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 8, 8);
+
+    let output = aeternum(&folder, &["--json", "-m", "mock-x4", input.to_str().unwrap()]);
+    let events = events(&output);
+
+    let order: Vec<&str> = events.iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .filter(|name| *name != "progress")
+        .collect();
+
+    assert_eq!(order, ["queued", "started", "finished"]);
+    assert_eq!(events_named(&events, "started")[0]["worker"], 0);
+}
+
+#[test]
+fn only_percentages_become_progress() {
+    let folder = TestFolder::new("only_percentages_become_progress");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 8, 8);
+
+    let output = aeternum(&folder, &["--json", "-m", "mock-x4", input.to_str().unwrap()]);
+    let events = events(&output);
+
+    let messages: Vec<&str> = events_named(&events, "progress").iter()
+        .map(|event| event["message"].as_str().unwrap())
+        .filter(|message| message.starts_with("Processing"))
+        .collect();
+
+    assert_eq!(
+        messages,
+        [
+            "Processing: 0.00%",
+            "Processing: 25.00%",
+            "Processing: 50.00%",
+            "Processing: 75.00%",
+            "Processing: 100.00%"
+        ]
+    );
+    assert!(!events.iter().any(|event| event.to_string().contains("queueC")));
+}
+
+#[test]
+fn names_outputs_after_the_input_model_and_scale() {
+    let folder = TestFolder::new("names_outputs");
+    let input = folder.join("holiday.png");
+    let out_folder = folder.join("out");
+
+    write_image(&input, 8, 8);
+    fs::create_dir_all(&out_folder).unwrap();
+
+    let output = aeternum(
+        &folder,
+        &["-m", "mock-x4", "-s", "2", "--format", "jpg", "-o", out_folder.to_str().unwrap(), input.to_str().unwrap()]
+    );
+
+    assert_eq!(output.status.code(), Some(0));
+
+    let expected = out_folder.join("holiday_mock-x4_x2.jpg");
+
+    // without --json the output path is all that's printed to stdout.
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), expected.to_str().unwrap());
+    assert!(expected.exists());
+}
+
+#[test]
+fn warns_when_the_output_format_cant_keep_the_metadata() {
+    let folder = TestFolder::new("warns_about_dropped_metadata");
+    let input = folder.join("photo.jpg");
+
+    // has EXIF to copy over.
+    write_rotated_jpeg(&input, 16, 12);
+
+    let png = aeternum(&folder, &["--json", "-m", "mock-x4", "--format", "png", input.to_str().unwrap()]);
+    let tiff = aeternum(&folder, &["--json", "-m", "mock-x4", "--format", "tiff", input.to_str().unwrap()]);
+
+    assert_eq!(png.status.code(), Some(0));
+    assert!(events_named(&events(&png), "warning").is_empty());
+
+    let tiff_events = events(&tiff);
+    let warnings = events_named(&tiff_events, "warning");
+
+    assert_eq!(tiff.status.code(), Some(0));
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0]["kind"], "FailedToCopyMetadata");
+}
+
+#[test]
+fn missing_input_exits_with_file_not_found() {
+    let folder = TestFolder::new("missing_input");
+    let input = folder.join("nope.png");
+
+    let output = aeternum(&folder, &["--json", input.to_str().unwrap()]);
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert_eq!(events_named(&events, "error")[0]["kind"], "FileNotFound");
+}
+
+#[test]
+fn unknown_model_exits_with_model_not_found() {
+    let folder = TestFolder::new("unknown_model");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 8, 8);
+
+    let output = aeternum(&folder, &["--json", "-m", "realesrgan-x4plus", input.to_str().unwrap()]);
+
+    assert_eq!(output.status.code(), Some(28));
+    assert_eq!(events_named(&events(&output), "error")[0]["kind"], "ModelNotFound");
+}
+
+#[test]
+fn backend_failure_exits_with_failed_to_upscale() {
+    let folder = TestFolder::new("backend_failure");
+    let input = folder.join("photo.png");
+
+    write_image(&input, 8, 8);
+
+    let output = aeternum(&folder, &["--json", "-m", "mock-fail", input.to_str().unwrap()]);
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(12));
+    assert_eq!(events_named(&events, "error")[0]["kind"], "FailedToUpscaleImage");
+    assert!(events_named(&events, "finished").is_empty());
+}
+
+#[test]
+fn batch_carries_on_after_a_failure() {
+    let folder = TestFolder::new("batch_failure");
+    let good = folder.join("good.png");
+    let missing = folder.join("missing.png");
+
+    write_image(&good, 8, 8);
+
+    let output = aeternum(&folder, &["--json", "-m", "mock-x4", missing.to_str().unwrap(), good.to_str().unwrap()]);
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert_eq!(events_named(&events, "finished").len(), 1);
+}
+
+#[test]
+fn dry_run_flags_colliding_outputs() {
+    let folder = TestFolder::new("dry_run_collisions");
+    let first = folder.join("a").join("photo.png");
+    let second = folder.join("b").join("photo.png");
+    let out_folder = folder.join("out");
+
+    for path in [&first, &second] {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        write_image(path, 8, 8);
+    }
+
+    let output = aeternum(
+        &folder,
+        &["--json", "--dry-run", "-m", "mock-x4", "-o", out_folder.to_str().unwrap(), first.to_str().unwrap(), second.to_str().unwrap()]
+    );
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(events[0]["job"]["errors"].as_array().unwrap().is_empty());
+    assert_eq!(events[1]["job"]["errors"].as_array().unwrap().len(), 1);
+    assert_eq!(events_named(&events, "error")[0]["kind"], "PlannedJobsWouldFail");
+    assert!(!out_folder.exists());
+}
+
+#[test]
+fn dry_run_checks_the_crop_against_each_image() {
+    let folder = TestFolder::new("dry_run_crop");
+    let small = folder.join("small.png");
+    let big = folder.join("big.png");
+
+    write_image(&small, 8, 8);
+    write_image(&big, 32, 32);
+
+    let output = aeternum(
+        &folder,
+        &["--json", "--dry-run", "-m", "mock-x4", "--crop", "0,0,16,16", small.to_str().unwrap(), big.to_str().unwrap()]
+    );
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(events[0]["job"]["errors"].as_array().unwrap().len(), 1);
+    assert!(events[1]["job"]["errors"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn dry_run_checks_the_crop_against_the_upright_image() {
+    let folder = TestFolder::new("dry_run_crop_upright");
+    let input = folder.join("rotated.jpg");
+
+    // stored as 32x8 but shown as 8x32.
+    write_rotated_jpeg(&input, 32, 8);
+
+    let fits = aeternum(&folder, &["--json", "--dry-run", "-m", "mock-x4", "--crop", "0,0,8,32", input.to_str().unwrap()]);
+    let too_wide = aeternum(&folder, &["--json", "--dry-run", "-m", "mock-x4", "--crop", "0,0,32,8", input.to_str().unwrap()]);
+
+    assert_eq!(fits.status.code(), Some(0));
+    assert!(events(&fits)[0]["job"]["errors"].as_array().unwrap().is_empty());
+
+    assert_eq!(too_wide.status.code(), Some(3));
+    assert_eq!(events(&too_wide)[0]["job"]["errors"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn each_image_uses_its_own_project_config() {
+    let folder = TestFolder::new("project_configs");
+    let project = folder.join("project");
+    let other = folder.join("other");
+
+    fs::create_dir_all(&project).unwrap();
+    fs::create_dir_all(&other).unwrap();
+    fs::write(project.join("aeternum.toml"), "[defaults]\nformat = \"JPG\"\n").unwrap();
+
+    write_image(&project.join("photo.png"), 8, 8);
+    write_image(&other.join("photo.png"), 8, 8);
+
+    let output = aeternum(
+        &folder,
+        &["--json", "--dry-run", "-m", "mock-x4", project.join("photo.png").to_str().unwrap(), other.join("photo.png").to_str().unwrap()]
+    );
+    let events = events(&output);
+
+    assert_eq!(output.status.code(), Some(0));
+    assert!(events[0]["job"]["output"].as_str().unwrap().ends_with(".jpg"));
+    assert!(!events[1]["job"]["output"].as_str().unwrap().ends_with(".jpg"));
+}